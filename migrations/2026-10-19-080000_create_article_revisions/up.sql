@@ -0,0 +1,16 @@
+CREATE TABLE article_revisions (
+    id SERIAL PRIMARY KEY,
+    article INTEGER NOT NULL REFERENCES articles ON DELETE CASCADE,
+    author INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    body TEXT NOT NULL,
+    changed_fields TEXT[] NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX article_revisions_article_idx ON article_revisions (article, id);
+
+INSERT INTO article_revisions (article, author, title, description, body, changed_fields, created_at)
+SELECT id, author, title, description, body, ARRAY['title', 'description', 'body'], created_at
+FROM articles;
@@ -0,0 +1,3 @@
+DROP INDEX articles_tag_list_idx;
+DROP TABLE article_tags;
+DROP TABLE tags;
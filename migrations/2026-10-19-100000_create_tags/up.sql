@@ -0,0 +1,36 @@
+CREATE TABLE tags (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE article_tags (
+    article INTEGER REFERENCES articles ON DELETE CASCADE,
+    tag INTEGER REFERENCES tags ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (article, tag)
+);
+
+CREATE INDEX article_tags_tag_idx ON article_tags (tag, created_at);
+CREATE INDEX articles_tag_list_idx ON articles USING GIN (tag_list);
+
+-- Normalize legacy tags the way the API does: trimmed, lowercased, without
+-- empty or repeated entries, in their original order.
+UPDATE articles SET tag_list = ARRAY(
+    SELECT name
+    FROM unnest(tag_list) WITH ORDINALITY AS t(raw, position)
+    CROSS JOIN LATERAL (SELECT lower(trim(raw)) AS name) AS normalized
+    WHERE name <> ''
+    GROUP BY name
+    ORDER BY min(position)
+);
+
+INSERT INTO tags (name)
+SELECT DISTINCT unnest(tag_list) FROM articles;
+
+INSERT INTO article_tags (article, tag, created_at)
+SELECT DISTINCT articles.id, tags.id, articles.created_at
+FROM articles
+CROSS JOIN LATERAL unnest(articles.tag_list) AS t(name)
+INNER JOIN tags ON tags.name = t.name;
@@ -0,0 +1,6 @@
+CREATE TABLE tag_follows (
+    follower INTEGER REFERENCES users ON DELETE CASCADE,
+    tag INTEGER REFERENCES tags ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (follower, tag)
+);
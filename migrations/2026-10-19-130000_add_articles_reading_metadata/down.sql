@@ -0,0 +1,5 @@
+DROP INDEX articles_reading_time_idx;
+ALTER TABLE articles
+    DROP COLUMN word_count,
+    DROP COLUMN reading_time,
+    DROP COLUMN toc;
@@ -0,0 +1,13 @@
+ALTER TABLE articles
+    ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN reading_time INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN toc JSONB;
+
+-- Approximate figures for existing articles; they are recomputed exactly on
+-- the next update. The table of contents stays empty until then.
+UPDATE articles
+SET word_count = coalesce(array_length(regexp_split_to_array(trim(body), '\s+'), 1), 0)
+WHERE trim(body) <> '';
+UPDATE articles SET reading_time = (word_count + 199) / 200;
+
+CREATE INDEX articles_reading_time_idx ON articles (reading_time);
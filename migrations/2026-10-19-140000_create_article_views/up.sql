@@ -0,0 +1,8 @@
+CREATE TABLE article_views (
+    article INTEGER REFERENCES articles ON DELETE CASCADE,
+    visitor TEXT NOT NULL,
+    viewed_on DATE NOT NULL DEFAULT CURRENT_DATE,
+    PRIMARY KEY (article, visitor, viewed_on)
+);
+
+CREATE INDEX article_views_viewed_on_idx ON article_views (viewed_on);
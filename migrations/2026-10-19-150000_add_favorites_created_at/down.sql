@@ -0,0 +1,3 @@
+DROP INDEX favorites_user_created_at_idx;
+DROP INDEX favorites_article_created_at_idx;
+ALTER TABLE favorites DROP COLUMN created_at;
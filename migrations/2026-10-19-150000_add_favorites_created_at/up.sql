@@ -0,0 +1,3 @@
+ALTER TABLE favorites ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
+CREATE INDEX favorites_article_created_at_idx ON favorites (article, created_at);
+CREATE INDEX favorites_user_created_at_idx ON favorites ("user", created_at);
@@ -0,0 +1,10 @@
+CREATE TABLE article_authors (
+    article INTEGER NOT NULL REFERENCES articles ON DELETE CASCADE,
+    "user" INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    invited_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    accepted_at TIMESTAMPTZ,
+    PRIMARY KEY (article, "user")
+);
+
+CREATE INDEX article_authors_user_idx ON article_authors ("user");
@@ -0,0 +1,2 @@
+DROP TABLE series_articles;
+DROP TABLE series;
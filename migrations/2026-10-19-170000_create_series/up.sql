@@ -0,0 +1,20 @@
+CREATE TABLE series (
+    id SERIAL PRIMARY KEY,
+    slug TEXT NOT NULL UNIQUE,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    owner INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+SELECT diesel_manage_updated_at('series');
+
+CREATE INDEX series_owner_idx ON series (owner);
+
+CREATE TABLE series_articles (
+    series INTEGER NOT NULL REFERENCES series ON DELETE CASCADE,
+    article INTEGER NOT NULL UNIQUE REFERENCES articles ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (series, article)
+);
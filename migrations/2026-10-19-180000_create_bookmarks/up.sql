@@ -0,0 +1,9 @@
+CREATE TABLE bookmarks (
+    "user" INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    article INTEGER NOT NULL REFERENCES articles ON DELETE CASCADE,
+    folder TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY ("user", article)
+);
+
+CREATE INDEX bookmarks_user_created_at_idx ON bookmarks ("user", created_at);
@@ -0,0 +1,4 @@
+ALTER TABLE comments DROP COLUMN reaction_counts;
+ALTER TABLE articles DROP COLUMN reaction_counts;
+DROP TABLE comment_reactions;
+DROP TABLE article_reactions;
@@ -0,0 +1,22 @@
+CREATE TABLE article_reactions (
+    article INTEGER NOT NULL REFERENCES articles ON DELETE CASCADE,
+    "user" INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    reaction TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (article, "user", reaction)
+);
+
+CREATE INDEX article_reactions_user_idx ON article_reactions ("user");
+
+CREATE TABLE comment_reactions (
+    comment INTEGER NOT NULL REFERENCES comments ON DELETE CASCADE,
+    "user" INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    reaction TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (comment, "user", reaction)
+);
+
+CREATE INDEX comment_reactions_user_idx ON comment_reactions ("user");
+
+ALTER TABLE articles ADD COLUMN reaction_counts JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE comments ADD COLUMN reaction_counts JSONB NOT NULL DEFAULT '{}';
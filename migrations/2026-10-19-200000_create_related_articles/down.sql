@@ -0,0 +1,2 @@
+DROP TABLE related_articles;
+DROP TABLE related_cache;
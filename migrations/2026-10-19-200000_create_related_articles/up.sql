@@ -0,0 +1,13 @@
+CREATE TABLE related_cache (
+    article INTEGER PRIMARY KEY REFERENCES articles ON DELETE CASCADE,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE related_articles (
+    article INTEGER NOT NULL REFERENCES articles ON DELETE CASCADE,
+    related INTEGER NOT NULL REFERENCES articles ON DELETE CASCADE,
+    score DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (article, related)
+);
+
+CREATE INDEX related_articles_related_idx ON related_articles (related);
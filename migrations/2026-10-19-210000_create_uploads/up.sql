@@ -0,0 +1,12 @@
+CREATE TABLE uploads (
+    id SERIAL PRIMARY KEY,
+    owner INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    hash TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    filename TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (owner, hash)
+);
+
+CREATE INDEX uploads_hash_idx ON uploads (hash);
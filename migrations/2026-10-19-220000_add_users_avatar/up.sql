@@ -0,0 +1,2 @@
+-- Upload hashes of the processed avatar, keyed by size and then format.
+ALTER TABLE users ADD COLUMN avatar JSONB;
@@ -0,0 +1 @@
+ALTER TABLE articles ADD COLUMN cover_image TEXT;
@@ -0,0 +1,6 @@
+CREATE TABLE feed_tokens (
+    "user" INTEGER PRIMARY KEY REFERENCES users ON DELETE CASCADE,
+    token TEXT NOT NULL UNIQUE
+        DEFAULT replace(gen_random_uuid()::text || gen_random_uuid()::text, '-', ''),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1 @@
+DROP TABLE sitemap_entries;
@@ -0,0 +1,32 @@
+-- Every URL listed in the sitemap. Rows are kept up to date as articles
+-- change, and sitemap pages are ranges of ids so serving one never scans
+-- the source tables.
+CREATE TABLE sitemap_entries (
+    id SERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    item INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    lastmod TIMESTAMPTZ NOT NULL,
+    UNIQUE (kind, item)
+);
+
+INSERT INTO sitemap_entries (kind, item, name, lastmod)
+SELECT 'article', id, slug, updated_at FROM articles ORDER BY id;
+
+-- Authors and accepted co-authors, as `sitemap::rebuild` lists them.
+INSERT INTO sitemap_entries (kind, item, name, lastmod)
+SELECT 'profile', users.id, users.username, max(articles.updated_at)
+FROM users
+INNER JOIN (SELECT id, author AS user_id FROM articles
+            UNION SELECT article, "user" FROM article_authors
+            WHERE accepted_at IS NOT NULL) AS authored
+        ON authored.user_id = users.id
+INNER JOIN articles ON articles.id = authored.id
+GROUP BY users.id ORDER BY users.id;
+
+INSERT INTO sitemap_entries (kind, item, name, lastmod)
+SELECT 'tag', tags.id, tags.name, max(articles.updated_at)
+FROM tags
+INNER JOIN article_tags ON article_tags.tag = tags.id
+INNER JOIN articles ON articles.id = article_tags.article
+GROUP BY tags.id ORDER BY tags.id;
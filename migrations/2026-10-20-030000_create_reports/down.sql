@@ -0,0 +1,8 @@
+DROP TABLE warnings;
+DROP TABLE reports;
+ALTER TABLE comments DROP COLUMN hidden_at;
+ALTER TABLE articles DROP COLUMN hidden_at;
+ALTER TABLE users DROP COLUMN suspended_at;
+UPDATE users SET role = 'user' WHERE role = 'moderator';
+ALTER TABLE users DROP CONSTRAINT users_role_check;
+ALTER TABLE users ADD CONSTRAINT users_role_check CHECK (role IN ('user', 'admin'));
@@ -0,0 +1,34 @@
+ALTER TABLE users DROP CONSTRAINT users_role_check;
+ALTER TABLE users ADD CONSTRAINT users_role_check
+    CHECK (role IN ('user', 'moderator', 'admin'));
+ALTER TABLE users ADD COLUMN suspended_at TIMESTAMPTZ;
+ALTER TABLE articles ADD COLUMN hidden_at TIMESTAMPTZ;
+ALTER TABLE comments ADD COLUMN hidden_at TIMESTAMPTZ;
+
+CREATE TABLE reports (
+       id SERIAL PRIMARY KEY,
+       -- NULL for reports filed automatically
+       reporter INTEGER REFERENCES users ON DELETE CASCADE,
+       target_type TEXT NOT NULL CHECK (target_type IN ('article', 'comment', 'profile')),
+       target_id INTEGER NOT NULL,
+       reason TEXT NOT NULL CHECK (reason IN ('spam', 'harassment', 'hate', 'sexual', 'violence', 'misinformation', 'other')),
+       details TEXT NOT NULL DEFAULT '',
+       status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'actioned', 'dismissed')),
+       action TEXT CHECK (action IN ('hide', 'remove', 'warn', 'suspend')),
+       resolved_by INTEGER REFERENCES users ON DELETE SET NULL,
+       resolved_at TIMESTAMPTZ,
+       created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE UNIQUE INDEX reports_open_key ON reports (reporter, target_type, target_id) WHERE status = 'open';
+CREATE INDEX reports_target_idx ON reports (target_type, target_id);
+CREATE INDEX reports_status_idx ON reports (status, created_at);
+
+CREATE TABLE warnings (
+       id SERIAL PRIMARY KEY,
+       "user" INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+       moderator INTEGER REFERENCES users ON DELETE SET NULL,
+       report INTEGER REFERENCES reports ON DELETE SET NULL,
+       message TEXT NOT NULL,
+       created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX warnings_user_idx ON warnings ("user", created_at);
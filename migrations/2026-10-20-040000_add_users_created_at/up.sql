@@ -0,0 +1,7 @@
+ALTER TABLE users ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
+-- Existing accounts are at least as old as the first thing they wrote.
+UPDATE users SET created_at = least(
+       created_at,
+       (SELECT min(created_at) FROM articles WHERE articles.author = users.id),
+       (SELECT min(created_at) FROM comments WHERE comments.author = users.id)
+);
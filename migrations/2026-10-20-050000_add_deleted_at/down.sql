@@ -0,0 +1,6 @@
+-- Whatever is still in the trash is gone for good.
+DELETE FROM comments WHERE deleted_at IS NOT NULL;
+DELETE FROM articles WHERE deleted_at IS NOT NULL;
+ALTER TABLE comments DROP COLUMN deleted_by;
+ALTER TABLE comments DROP COLUMN deleted_at;
+ALTER TABLE articles DROP COLUMN deleted_at;
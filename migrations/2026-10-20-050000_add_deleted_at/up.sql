@@ -0,0 +1,6 @@
+ALTER TABLE articles ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE comments ADD COLUMN deleted_at TIMESTAMPTZ;
+-- The comment or article author, whose trash the comment ends up in
+ALTER TABLE comments ADD COLUMN deleted_by INTEGER REFERENCES users ON DELETE SET NULL;
+CREATE INDEX articles_deleted_at_idx ON articles (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX comments_deleted_at_idx ON comments (deleted_at) WHERE deleted_at IS NOT NULL;
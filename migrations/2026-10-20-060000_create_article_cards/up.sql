@@ -0,0 +1,7 @@
+-- The social card last drawn for an article, stored as a blob. It is drawn
+-- again once what it shows, summed up by `drawn_from`, changes.
+CREATE TABLE article_cards (
+    article INTEGER PRIMARY KEY REFERENCES articles ON DELETE CASCADE,
+    hash TEXT NOT NULL,
+    drawn_from TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE comments DROP COLUMN removed_by;
+ALTER TABLE articles DROP COLUMN removed_by;
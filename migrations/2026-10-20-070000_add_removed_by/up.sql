@@ -0,0 +1,4 @@
+-- The moderator who removed an article or comment. It stays in its author's
+-- trash like anything else deleted.
+ALTER TABLE articles ADD COLUMN removed_by INTEGER REFERENCES users ON DELETE SET NULL;
+ALTER TABLE comments ADD COLUMN removed_by INTEGER REFERENCES users ON DELETE SET NULL;
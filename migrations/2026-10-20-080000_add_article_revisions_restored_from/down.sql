@@ -0,0 +1 @@
+ALTER TABLE article_revisions DROP COLUMN restored_from;
@@ -0,0 +1,4 @@
+-- The revision a restore brought back, so that restoring is recorded even
+-- when it changes nothing.
+ALTER TABLE article_revisions
+    ADD COLUMN restored_from INTEGER REFERENCES article_revisions ON DELETE SET NULL;
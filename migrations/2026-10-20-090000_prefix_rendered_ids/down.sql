@@ -0,0 +1,9 @@
+UPDATE articles SET body_html = NULL;
+UPDATE articles
+SET toc = (
+    SELECT jsonb_agg(
+        heading || jsonb_build_object('anchor', substr(heading->>'anchor', length('user-content-') + 1))
+        ORDER BY position)
+    FROM jsonb_array_elements(toc) WITH ORDINALITY AS headings(heading, position)
+)
+WHERE jsonb_array_length(toc) > 0;
@@ -0,0 +1,11 @@
+-- Rendered bodies are re-rendered with prefixed ids on their next read; the
+-- stored tables of contents get the same prefix on their anchors.
+UPDATE articles SET body_html = NULL;
+UPDATE articles
+SET toc = (
+    SELECT jsonb_agg(
+        heading || jsonb_build_object('anchor', 'user-content-' || (heading->>'anchor'))
+        ORDER BY position)
+    FROM jsonb_array_elements(toc) WITH ORDINALITY AS headings(heading, position)
+)
+WHERE jsonb_array_length(toc) > 0;
@@ -0,0 +1,112 @@
+use crate::{
+    api::uploads::read_file,
+    archive,
+    auth::Auth,
+    db::{
+        archive::{self as db_archive, Report},
+        Crud, User,
+    },
+    errors::Errors,
+    Pool,
+};
+use actix_multipart::Multipart;
+use actix_web::{
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    web, Error, HttpResponse, Result,
+};
+use diesel::pg::PgConnection;
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+/// Archives larger than this are rejected by the import endpoint; bigger
+/// migrations can use the `import` command instead.
+const MAX_IMPORT_SIZE: usize = 50 * 1024 * 1024;
+
+fn require_admin(conn: &PgConnection, user_id: i32) -> Result<(), Errors> {
+    if User::read(conn, user_id)?.is_admin() {
+        Ok(())
+    } else {
+        Err(Errors::new().code(StatusCode::FORBIDDEN))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    author: Option<String>,
+}
+
+/// Every article, or one author's, as a gzipped tarball of Markdown files
+#[get("/admin/export")]
+pub async fn export(
+    auth: Auth,
+    query: web::Query<ExportQuery>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let author = query.into_inner().author;
+    let data = web::block(move || {
+        let conn = pool.get().unwrap();
+        require_admin(&conn, auth.claims.id)?;
+        let documents = db_archive::export(&conn, author.as_deref())?;
+        let encoder = GzEncoder::new(Vec::new(), Compression::default());
+        archive::write_tar(encoder, &documents)
+            .and_then(|encoder| encoder.finish())
+            .map_err(|e| {
+                error!("failed to write export: {}", e);
+                Errors::new().code(StatusCode::INTERNAL_SERVER_ERROR)
+            })
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok()
+        .header(CONTENT_TYPE, "application/gzip")
+        .header(
+            CONTENT_DISPOSITION,
+            "attachment; filename=\"articles.tar.gz\"",
+        )
+        .body(data))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportQuery {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct ImportResult {
+    import: Report,
+}
+
+/// Imports a tarball in the format of the export, given as the `file` field
+#[post("/admin/import")]
+pub async fn import(
+    payload: Multipart,
+    auth: Auth,
+    query: web::Query<ImportQuery>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let dry_run = query.dry_run;
+    let (data, _) = read_file(payload, MAX_IMPORT_SIZE)
+        .await?
+        .ok_or_else(|| Errors::with_field("file", "is missing"))?;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        require_admin(&conn, auth.claims.id)?;
+        let entries = archive::read_tar(&data[..]).map_err(|_| {
+            let message = format!(
+                "must be a tar or tar.gz archive of at most {} bytes unpacked",
+                archive::MAX_UNPACKED_SIZE
+            );
+            Errors::with_field("file", &message)
+        })?;
+        Ok::<_, Errors>(db_archive::import(&conn, entries, dry_run)?)
+    })
+    .await
+    .map(|import| ImportResult { import })
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
@@ -1,13 +1,27 @@
 use crate::{
+    api::moderation::screen,
     auth::Auth,
     db::{
-        article::{self, Article, ArticleForm, ArticleQuery, ArticleUpdate},
-        comment::{self, Comment},
+        self,
+        article::{self, Article, ArticleForm, ArticleQuery, ArticleUpdateData},
+        coauthor::{self, CoAuthor},
+        comment::{self, Comment, TrashedComment},
+        report, revision, Crud, Page, User,
     },
     errors::Errors,
+    filter::{Kind, Pipeline, Submission},
+    validation::{
+        normalize_tags, validate_cover_image, validate_cover_upload, validate_length,
+        validate_tags, MAX_DESCRIPTION_LENGTH, MAX_TITLE_LENGTH,
+    },
+    views::ViewTracker,
     Pool,
 };
-use actix_web::{http::StatusCode, web, Error, HttpResponse, Result};
+use actix_web::{
+    http::{header::LOCATION, StatusCode},
+    web, Error, HttpRequest, HttpResponse, Result,
+};
+use diesel::{pg::PgConnection, result::Error as DieselError, Connection};
 use serde::{ Serialize, Deserialize };
 use validator::Validate;
 
@@ -24,6 +38,25 @@ pub struct NewArticleData {
     description: String,
     body: String,
     tag_list: Option<Vec<String>>,
+    cover_image: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpdateArticle {
+    article: UpdateArticleData,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateArticleData {
+    title: Option<String>,
+    description: Option<String>,
+    body: Option<String>,
+    tag_list: Option<Vec<String>>,
+    add_tags: Option<Vec<String>>,
+    remove_tags: Option<Vec<String>>,
+    /// An empty string removes the cover image.
+    cover_image: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -48,6 +81,11 @@ impl ArticleResult {
 }
 
 
+#[derive(Serialize, Deserialize)]
+pub struct SlugResult {
+    slug: String,
+}
+
 #[derive(Serialize,Deserialize)]
 pub struct CommentResult {
     comment: Comment
@@ -59,6 +97,28 @@ impl CommentResult {
     }
 }
 
+/// Answers 202 Accepted for writes held for review, which the author gets
+/// back with `hiddenAt` set.
+fn written<T: Serialize>(held: bool, result: T) -> HttpResponse {
+    if held {
+        HttpResponse::Accepted().json(result)
+    } else {
+        HttpResponse::Ok().json(result)
+    }
+}
+
+/// Hides an article the content filters held and files it for review.
+fn hold_article(
+    conn: &PgConnection,
+    slug: &str,
+    user_id: i32,
+    reason: &str,
+) -> Result<Article, Errors> {
+    let (id, _) = report::find_target(conn, report::ARTICLE, slug)?;
+    report::hold(conn, report::ARTICLE, id, reason)?;
+    Ok(article::get_article(conn, slug, Some(user_id))?)
+}
+
 #[get("/articles")]
 pub async fn list_articles(
     query: web::Query<ArticleQuery>,
@@ -93,73 +153,229 @@ pub async fn feed_articles(
 
 #[get("/articles/{slug}")]
 pub async fn get_article(
+    req: HttpRequest,
     info: web::Path<String>,
+    auth: Option<Auth>,
     pool: web::Data<Pool>,
+    views: web::Data<ViewTracker>,
 ) -> Result<HttpResponse, Error> {
     let slug = info.into_inner();
+    let user_id = auth.map(|a| a.claims.id);
+    let visitor = views.visitor(&req, user_id);
     let result = web::block(move || {
         let conn = pool.get().unwrap();
-        article::get_article(&conn, &slug)
+        match article::get_article(&conn, &slug, user_id) {
+            Err(DieselError::NotFound) => article::current_slug(&conn, &slug).map(Err),
+            result => result.map(Ok),
+        }
     })
     .await
-    .map(ArticleResult::new)
     .map_err(Errors::from)?;
 
-    Ok(HttpResponse::Ok().json(result))
+    match result {
+        Ok(article) => {
+            views.record(article.id, visitor);
+            Ok(HttpResponse::Ok().json(ArticleResult::new(article)))
+        }
+        Err(slug) => Ok(HttpResponse::MovedPermanently()
+            .header(LOCATION, format!("/api/articles/{}", slug))
+            .json(SlugResult { slug })),
+    }
 }
 
 #[post("/articles")]
 pub async fn create_article(
     new_article: web::Json<NewArticle>,
     auth: Auth,
+    filters: web::Data<Pipeline>,
     pool: web::Data<Pool>,
 ) -> Result<HttpResponse, Error> {
     let article = new_article.into_inner().article;
     article.validate().map_err(Errors::from)?;
 
-    let slug = slug::slugify(&article.title);
-    let article_form = ArticleForm {
-        slug,
+    let tag_list = normalize_tags(article.tag_list.unwrap_or_default());
+    let cover_image = article.cover_image.filter(|url| !url.is_empty());
+    let mut errors = Errors::new();
+    validate_length(&mut errors, "title", &article.title, MAX_TITLE_LENGTH);
+    validate_length(
+        &mut errors,
+        "description",
+        &article.description,
+        MAX_DESCRIPTION_LENGTH,
+    );
+    validate_tags(&mut errors, "tagList", &tag_list);
+    if let Some(ref url) = cover_image {
+        validate_cover_image(&mut errors, url);
+    }
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+
+    let mut article_form = ArticleForm {
+        slug: String::new(),
         title: article.title,
         description: article.description,
         body: article.body,
-        tag_list: article.tag_list.unwrap_or(vec![]),
+        tag_list,
         author: auth.claims.id,
+        cover_image,
     };
 
-    let article = web::block(move || {
+    let (article, held) = web::block(move || {
         let conn = pool.get().unwrap();
-        article::create(&conn, &article_form)
+        let author = User::read(&conn, article_form.author)?;
+        if let Some(ref url) = article_form.cover_image {
+            let mut errors = Errors::new();
+            validate_cover_upload(&conn, &mut errors, author.id, url)?;
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+        }
+        let submission = Submission {
+            kind: Kind::Article,
+            author: &author,
+            texts: vec![
+                ("title", &article_form.title),
+                ("description", &article_form.description),
+                ("body", &article_form.body),
+            ],
+            body: Some(&article_form.body),
+            editing: None,
+        };
+        let held = screen(&conn, &filters, &submission)?;
+        conn.transaction::<_, Errors, _>(|| {
+            let title = article_form.title.clone();
+            let article = article::with_unique_slug(&conn, &title, None, |slug| {
+                article_form.slug = slug;
+                article::create(&conn, &article_form)
+            })?;
+            match held {
+                Some(reason) => Ok((
+                    hold_article(&conn, &article.slug, author.id, &reason)?,
+                    true,
+                )),
+                None => Ok((article, false)),
+            }
+        })
     })
     .await
-    .map(ArticleResult::new)
     .map_err(Errors::from)?;
 
-    Ok(HttpResponse::Ok().json(article))
+    Ok(written(held, ArticleResult::new(article)))
 }
 
 #[put("articles/{slug}")]
 pub async fn update_article(
     auth: Auth,
     info: web::Path<String>,
-    article: web::Json<ArticleUpdate>,
+    article: web::Json<UpdateArticle>,
+    filters: web::Data<Pipeline>,
     pool: web::Data<Pool>,
 ) -> Result<HttpResponse, Error> {
-    let mut article = article.into_inner().article;
+    let data = article.into_inner().article;
     let slug = info.into_inner();
-    if let Some(ref title) = article.title {
-        article.slug = Some(slug::slugify(title));
-    }
     let user_id = auth.claims.id;
 
-    let result = web::block(move || {
+    let tag_list = data.tag_list.map(normalize_tags);
+    let add_tags = data.add_tags.map(normalize_tags);
+    let remove_tags = data.remove_tags.map(normalize_tags);
+    let mut errors = Errors::new();
+    if let Some(ref title) = data.title {
+        validate_length(&mut errors, "title", title, MAX_TITLE_LENGTH);
+    }
+    if let Some(ref description) = data.description {
+        validate_length(&mut errors, "description", description, MAX_DESCRIPTION_LENGTH);
+    }
+    if let Some(ref list) = tag_list {
+        validate_tags(&mut errors, "tagList", list);
+    }
+    if let Some(ref list) = add_tags {
+        validate_tags(&mut errors, "addTags", list);
+    }
+    let cover_image = data
+        .cover_image
+        .map(|url| Some(url).filter(|url| !url.is_empty()));
+    if let Some(Some(ref url)) = cover_image {
+        validate_cover_image(&mut errors, url);
+    }
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+
+    let mut article = ArticleUpdateData {
+        slug: None,
+        title: data.title,
+        description: data.description,
+        body: data.body,
+        tag_list: None,
+        cover_image,
+    };
+
+    let (result, held) = web::block(move || {
         let conn = pool.get().unwrap();
-        article::update(&conn, &slug, user_id, &article)
+        let author = User::read(&conn, user_id)?;
+        if let Some(Some(ref url)) = article.cover_image {
+            let mut errors = Errors::new();
+            validate_cover_upload(&conn, &mut errors, author.id, url)?;
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+        }
+        let submission = Submission {
+            kind: Kind::Article,
+            author: &author,
+            texts: [
+                ("title", &article.title),
+                ("description", &article.description),
+                ("body", &article.body),
+            ]
+            .iter()
+            .filter_map(|(field, text)| Some((*field, text.as_deref()?)))
+            .collect(),
+            body: article.body.as_deref(),
+            editing: Some(&slug),
+        };
+        let held = screen(&conn, &filters, &submission)?;
+        if tag_list.is_some() || add_tags.is_some() || remove_tags.is_some() {
+            let mut list = match tag_list {
+                Some(list) => list,
+                None => article::article_tags(&conn, &slug)?,
+            };
+            for tag in add_tags.unwrap_or_default() {
+                if !list.contains(&tag) {
+                    list.push(tag);
+                }
+            }
+            let remove_tags = remove_tags.unwrap_or_default();
+            list.retain(|t| !remove_tags.contains(t));
+
+            let mut errors = Errors::new();
+            validate_tags(&mut errors, "tagList", &list);
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+            article.tag_list = Some(list);
+        }
+        conn.transaction::<_, Errors, _>(|| {
+            let article = match article.title.clone() {
+                Some(title) => article::with_unique_slug(&conn, &title, Some(&slug), |new_slug| {
+                    article.slug = Some(new_slug);
+                    article::update(&conn, &slug, user_id, &article)
+                })?,
+                None => article::update(&conn, &slug, user_id, &article)?,
+            };
+            match held {
+                Some(reason) => Ok((
+                    hold_article(&conn, &article.slug, user_id, &reason)?,
+                    true,
+                )),
+                None => Ok((article, false)),
+            }
+        })
     })
     .await
-    .map(ArticleResult::new)
     .map_err(Errors::from)?;
-    Ok(HttpResponse::Ok().json(result))
+    Ok(written(held, ArticleResult::new(result)))
 }
 
 #[delete("articles/{slug}")]
@@ -186,29 +402,48 @@ pub async fn add_comment(
     info: web::Path<String>,
     auth: Auth,
     comment: web::Json<NewComment>,
+    filters: web::Data<Pipeline>,
     pool: web::Data<Pool>,
 ) -> Result<HttpResponse, Error> {
     let user_id = auth.claims.id;
     let slug = info.into_inner();
     let body = comment.comment.body.clone();
 
-    let result = web::block(move || {
+    let (result, held) = web::block(move || {
         let conn = pool.get().unwrap();
-        comment::add_comment(&conn, user_id, &slug, &body)
+        let author = User::read(&conn, user_id)?;
+        let submission = Submission {
+            kind: Kind::Comment,
+            author: &author,
+            texts: vec![("body", &body)],
+            body: Some(&body),
+            editing: None,
+        };
+        let held = screen(&conn, &filters, &submission)?;
+        conn.transaction::<_, Errors, _>(|| {
+            let comment = comment::add_comment(&conn, user_id, &slug, &body)?;
+            match held {
+                Some(reason) => {
+                    report::hold(&conn, report::COMMENT, comment.id, &reason)?;
+                    let comment = comment::get_comment(&conn, Some(user_id), &slug, comment.id)?;
+                    Ok((comment, true))
+                }
+                None => Ok((comment, false)),
+            }
+        })
     })
     .await
-    .map(CommentResult::new)
     .map_err(Errors::from)?;
-    Ok(HttpResponse::Ok().json(result))
+    Ok(written(held, CommentResult::new(result)))
 }
 
 #[get("/articles/{slug}/comments")]
 pub async fn get_comments(
     info: web::Path<String>,
-    auth: Auth,
+    auth: Option<Auth>,
     pool: web::Data<Pool>,
 ) -> Result<HttpResponse, Error> {
-    let user_id = auth.claims.id;
+    let user_id = auth.map(|a| a.claims.id);
     let slug = info.into_inner();
     let result = web::block(move || {
         let conn = pool.get().unwrap();
@@ -237,6 +472,64 @@ pub async fn delete_comment(
     Ok(HttpResponse::new(StatusCode::OK))
 }
 
+#[derive(Serialize)]
+pub struct TrashResult {
+    articles: Vec<Article>,
+    comments: Vec<TrashedComment>,
+}
+
+/// Deleted articles and comments of the current user, until they are purged
+#[get("/user/trash")]
+pub async fn trash(auth: Auth, pool: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    let user_id = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        Ok::<_, DieselError>(TrashResult {
+            articles: article::trash(&conn, user_id)?,
+            comments: comment::trash(&conn, user_id)?,
+        })
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/articles/{slug}/restore")]
+pub async fn restore_article(
+    info: web::Path<String>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let user_id = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        article::restore(&conn, user_id, &slug)
+    })
+    .await
+    .map(ArticleResult::new)
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/articles/{slug}/comments/{id}/restore")]
+pub async fn restore_comment(
+    info: web::Path<(String, i32)>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let (slug, comment_id) = (info.0.clone(), info.1);
+    let user_id = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        comment::restore(&conn, user_id, &slug, comment_id)
+    })
+    .await
+    .map(CommentResult::new)
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
 #[post("/articles/{slug}/favorite")]
 pub async fn favorite(
     info: web::Path<String>,
@@ -273,21 +566,344 @@ pub async fn unfavorite(
     Ok(HttpResponse::Ok().json(result))
 }
 
+const MAX_FOLDER_LENGTH: usize = 64;
 
-#[derive(Serialize, Deserialize)]
-pub struct TagsResult {
-    tags: Vec<String>
+#[derive(Deserialize, Debug)]
+pub struct NewBookmark {
+    bookmark: NewBookmarkData,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NewBookmarkData {
+    folder: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BookmarksQuery {
+    folder: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[post("/articles/{slug}/bookmark")]
+pub async fn bookmark(
+    info: web::Path<String>,
+    bookmark: Option<web::Json<NewBookmark>>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let folder = bookmark
+        .and_then(|b| b.into_inner().bookmark.folder)
+        .map(|f| f.trim().to_owned())
+        .filter(|f| !f.is_empty());
+    if folder.as_ref().is_some_and(|f| f.chars().count() > MAX_FOLDER_LENGTH) {
+        let mut errors = Errors::new();
+        errors.push_error(
+            "folder",
+            &format!("cannot be longer than {} characters", MAX_FOLDER_LENGTH),
+        );
+        return Err(errors.into());
+    }
+    let user_id = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        article::bookmark(&conn, user_id, &slug, folder.as_deref())
+    })
+    .await
+    .map(ArticleResult::new)
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
 }
 
-#[get("/tags")]
-pub async fn tags(pool: web::Data<Pool>) -> Result<HttpResponse, Error> {
+#[delete("/articles/{slug}/bookmark")]
+pub async fn unbookmark(
+    info: web::Path<String>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let user_id = auth.claims.id;
     let result = web::block(move || {
         let conn = pool.get().unwrap();
-        article::tag_list(&conn)
+        article::unbookmark(&conn, user_id, &slug)
     })
     .await
-    .map(|tags| TagsResult{tags})
+    .map(ArticleResult::new)
     .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
 
+#[get("/user/bookmarks")]
+pub async fn bookmarks(
+    query: web::Query<BookmarksQuery>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    let page = Page {
+        limit: query.limit,
+        offset: query.offset,
+    };
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        article::bookmarks(&conn, auth.claims.id, query.folder.as_deref(), &page)
+    })
+    .await
+    .map_err(Errors::from)?;
     Ok(HttpResponse::Ok().json(result))
 }
+
+const MAX_RELATED: i64 = 20;
+
+#[derive(Deserialize, Debug)]
+pub struct RelatedQuery {
+    limit: Option<i64>,
+}
+
+#[get("/articles/{slug}/related")]
+pub async fn related_articles(
+    info: web::Path<String>,
+    query: web::Query<RelatedQuery>,
+    auth: Option<Auth>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let limit = query.limit.unwrap_or(5).clamp(1, MAX_RELATED);
+    let user_id = auth.map(|a| a.claims.id);
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        article::related(&conn, &slug, user_id, limit)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/articles/{slug}/favorites")]
+pub async fn list_favorites(
+    info: web::Path<String>,
+    query: web::Query<Page>,
+    auth: Option<Auth>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let user_id = auth.map(|a| a.claims.id);
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        db::favorite::list(&conn, &slug, &query, user_id)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InviteAuthor {
+    author: InviteAuthorData,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InviteAuthorData {
+    username: String,
+}
+
+#[derive(Serialize)]
+pub struct CoAuthorResult {
+    author: CoAuthor,
+}
+
+#[get("/articles/{slug}/authors")]
+pub async fn list_authors(
+    info: web::Path<String>,
+    auth: Option<Auth>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let user_id = auth.map(|a| a.claims.id);
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        coauthor::list(&conn, &slug, user_id)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/articles/{slug}/authors")]
+pub async fn invite_author(
+    info: web::Path<String>,
+    invite: web::Json<InviteAuthor>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let username = invite.into_inner().author.username;
+    if username == auth.claims.username {
+        let mut errors = Errors::new();
+        errors.push_error("username", "can't invite the article's author");
+        return Err(errors.into());
+    }
+    let user_id = auth.claims.id;
+    let author = web::block(move || {
+        let conn = pool.get().unwrap();
+        coauthor::invite(&conn, &slug, user_id, &username)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(CoAuthorResult { author }))
+}
+
+#[post("/articles/{slug}/authors/accept")]
+pub async fn accept_author(
+    info: web::Path<String>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let user_id = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        coauthor::accept(&conn, &slug, user_id)?;
+        article::get_article(&conn, &slug, Some(user_id))
+    })
+    .await
+    .map(ArticleResult::new)
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[delete("/articles/{slug}/authors/{username}")]
+pub async fn remove_author(
+    info: web::Path<(String, String)>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let (slug, username) = info.into_inner();
+    let user_id = auth.claims.id;
+    web::block(move || {
+        let conn = pool.get().unwrap();
+        coauthor::remove(&conn, &slug, user_id, &username)
+    })
+    .await
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[get("/user/invitations")]
+pub async fn invitations(
+    query: web::Query<Page>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        article::invitations(&conn, auth.claims.id, &query)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    from: i32,
+    to: i32,
+}
+
+#[derive(Serialize)]
+pub struct DiffResult {
+    diff: revision::RevisionDiff,
+}
+
+#[get("/articles/{slug}/revisions")]
+pub async fn list_revisions(
+    info: web::Path<String>,
+    query: web::Query<Page>,
+    auth: Option<Auth>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let user_id = auth.map(|a| a.claims.id);
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        revision::list(&conn, &slug, &query, user_id)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/articles/{slug}/revisions/diff")]
+pub async fn diff_revisions(
+    info: web::Path<String>,
+    query: web::Query<DiffQuery>,
+    auth: Option<Auth>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let user_id = auth.map(|a| a.claims.id);
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        revision::diff(&conn, &slug, query.from, query.to, user_id)
+    })
+    .await
+    .map(|diff| DiffResult { diff })
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/articles/{slug}/revisions/{id}/restore")]
+pub async fn restore_revision(
+    info: web::Path<(String, i32)>,
+    auth: Auth,
+    filters: web::Data<Pipeline>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let (slug, revision_id) = (info.0.clone(), info.1);
+    let user_id = auth.claims.id;
+    let (result, held) = web::block(move || {
+        let conn = pool.get().unwrap();
+        let author = User::read(&conn, user_id)?;
+        let revision = revision::find(&conn, &slug, revision_id, Some(user_id))?;
+        let submission = Submission {
+            kind: Kind::Article,
+            author: &author,
+            texts: vec![
+                ("title", &revision.title),
+                ("description", &revision.description),
+                ("body", &revision.body),
+            ],
+            body: Some(&revision.body),
+            editing: Some(&slug),
+        };
+        let held = screen(&conn, &filters, &submission)?;
+        let title = revision.title.clone();
+        let mut article = ArticleUpdateData {
+            slug: None,
+            title: Some(revision.title),
+            description: Some(revision.description),
+            body: Some(revision.body),
+            tag_list: None,
+            cover_image: None,
+        };
+        conn.transaction::<_, Errors, _>(|| {
+            let article = article::with_unique_slug(&conn, &title, Some(&slug), |new_slug| {
+                article.slug = Some(new_slug);
+                article::restore_revision(&conn, &slug, user_id, &article, revision_id)
+            })?;
+            match held {
+                Some(reason) => Ok((
+                    hold_article(&conn, &article.slug, user_id, &reason)?,
+                    true,
+                )),
+                None => Ok((article, false)),
+            }
+        })
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(written(held, ArticleResult::new(result)))
+}
@@ -0,0 +1,288 @@
+use crate::{
+    api::meta::{encode, Site},
+    auth::Auth,
+    db::{
+        article::{self, ArticleQuery, Articles},
+        feed_token, tag, User,
+    },
+    errors::Errors,
+    feed::{Entry, Feed, Format},
+    markdown, Pool,
+};
+use actix_web::{
+    http::{
+        header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+        StatusCode,
+    },
+    web, Error, HttpRequest, HttpResponse, Result,
+};
+use chrono::DateTime;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+fn format(extension: &str) -> Result<Format, Errors> {
+    Format::parse(extension).ok_or_else(|| Errors::new().code(StatusCode::NOT_FOUND))
+}
+
+fn not_found(e: Errors) -> Errors {
+    e.code(StatusCode::NOT_FOUND)
+}
+
+/// Points root-relative links and images in `html`, such as uploads, at the
+/// site, since feed readers show entries away from it.
+fn absolute_links(site: &Site, html: &str) -> String {
+    let mut html = html.to_string();
+    for attribute in &["src=\"", "href=\""] {
+        let mut absolute = String::with_capacity(html.len());
+        let mut rest = html.as_str();
+        while let Some(i) = rest.find(attribute) {
+            let (head, tail) = rest.split_at(i + attribute.len());
+            absolute.push_str(head);
+            if tail.starts_with('/') && !tail.starts_with("//") {
+                absolute.push_str(&site.link(""));
+            }
+            rest = tail;
+        }
+        absolute.push_str(rest);
+        html = absolute;
+    }
+    html
+}
+
+fn build(site: &Site, title: String, link: String, path: &str, articles: Articles) -> Feed {
+    let entries = articles
+        .articles
+        .into_iter()
+        .map(|article| Entry {
+            id: site.tag_uri(article.created_at, &format!("article-{}", article.id)),
+            link: site.article_url(&article.slug),
+            content: absolute_links(
+                site,
+                &match article.body_html {
+                    Some(html) => html,
+                    None => markdown::render(&article.body),
+                },
+            ),
+            summary: article.description,
+            title: article.title,
+            author_link: site.profile_url(&article.author.username),
+            author: article.author.username,
+            published: article.created_at,
+            updated: article.updated_at,
+            categories: article.tag_list,
+        })
+        .collect();
+    Feed {
+        title,
+        link,
+        self_link: site.link(path),
+        entries,
+    }
+}
+
+/// Renders `feed`, answering with 304 Not Modified when the client already
+/// has this version.
+fn respond(req: &HttpRequest, feed: Feed, format: Format) -> HttpResponse {
+    let body = feed.render(format);
+    let etag = format!("\"{:x}\"", Sha256::digest(body.as_bytes()));
+    let updated = feed.updated();
+    let last_modified = updated.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+
+    let headers = req.headers();
+    let not_modified = match headers.get(IF_NONE_MATCH) {
+        Some(tags) => tags
+            .to_str()
+            .is_ok_and(|tags| tags.split(',').any(|t| t.trim() == etag || t.trim() == "*")),
+        None => headers
+            .get(IF_MODIFIED_SINCE)
+            .and_then(|since| since.to_str().ok())
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+            .is_some_and(|since| updated.timestamp() <= since.timestamp()),
+    };
+
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .header(ETAG, etag)
+        .header(LAST_MODIFIED, last_modified);
+    if not_modified {
+        return response.finish();
+    }
+    response
+        .header(CONTENT_TYPE, format.content_type())
+        .body(body)
+}
+
+#[get("/feeds/articles.{format}")]
+pub async fn articles_feed(
+    req: HttpRequest,
+    info: web::Path<String>,
+    site: web::Data<Site>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let format = format(&info)?;
+    let articles = web::block(move || {
+        let conn = pool.get().unwrap();
+        article::list_articles(&conn, &ArticleQuery::default(), None)
+    })
+    .await
+    .map_err(Errors::from)?;
+
+    let path = format!("/feeds/articles.{}", format.extension());
+    let feed = build(
+        &site,
+        "Recent articles".to_string(),
+        site.link("/"),
+        &path,
+        articles,
+    );
+    Ok(respond(&req, feed, format))
+}
+
+#[get("/feeds/authors/{username}.{format}")]
+pub async fn author_feed(
+    req: HttpRequest,
+    info: web::Path<(String, String)>,
+    site: web::Data<Site>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let (username, extension) = info.into_inner();
+    let format = format(&extension)?;
+    let author = username.clone();
+    let articles = web::block(move || {
+        let conn = pool.get().unwrap();
+        User::with_username(&conn, &author)?;
+        let query = ArticleQuery {
+            author: Some(author),
+            ..ArticleQuery::default()
+        };
+        article::list_articles(&conn, &query, None)
+    })
+    .await
+    .map_err(|e| not_found(e.into()))?;
+
+    let path = format!("/feeds/authors/{}.{}", username, format.extension());
+    let title = format!("Articles by {}", username);
+    let feed = build(&site, title, site.profile_url(&username), &path, articles);
+    Ok(respond(&req, feed, format))
+}
+
+#[get("/feeds/tags/{tag}.{format}")]
+pub async fn tag_feed(
+    req: HttpRequest,
+    info: web::Path<(String, String)>,
+    site: web::Data<Site>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let (name, extension) = info.into_inner();
+    let format = format(&extension)?;
+    let tag = tag::normalize(&name);
+    let query = ArticleQuery {
+        tag: Some(tag.clone()),
+        ..ArticleQuery::default()
+    };
+    let articles = web::block(move || {
+        let conn = pool.get().unwrap();
+        article::list_articles(&conn, &query, None)
+    })
+    .await
+    .map_err(Errors::from)?;
+
+    let path = format!("/feeds/tags/{}.{}", encode(&tag), format.extension());
+    let title = format!("Articles tagged {}", tag);
+    let feed = build(&site, title, site.tag_url(&tag), &path, articles);
+    Ok(respond(&req, feed, format))
+}
+
+/// The personal feed, for readers that cannot log in. The token in the URL
+/// stands in for the user's credentials.
+#[get("/feeds/personal/{token}.{format}")]
+pub async fn personal_feed(
+    req: HttpRequest,
+    info: web::Path<(String, String)>,
+    site: web::Data<Site>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let (token, extension) = info.into_inner();
+    let format = format(&extension)?;
+    let path = format!("/feeds/personal/{}.{}", token, format.extension());
+    let articles = web::block(move || {
+        let conn = pool.get().unwrap();
+        let user = feed_token::user(&conn, &token)?;
+        article::feed(&conn, &ArticleQuery::default(), user)
+    })
+    .await
+    .map_err(|e| not_found(e.into()))?;
+
+    let feed = build(
+        &site,
+        "Your feed".to_string(),
+        site.link("/"),
+        &path,
+        articles,
+    );
+    Ok(respond(&req, feed, format))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedToken {
+    token: String,
+    atom: String,
+    rss: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedTokenResult {
+    feed_token: FeedToken,
+}
+
+impl FeedTokenResult {
+    fn new(site: &Site, token: String) -> Self {
+        let url = |format: Format| {
+            site.link(&format!("/feeds/personal/{}.{}", token, format.extension()))
+        };
+        FeedTokenResult {
+            feed_token: FeedToken {
+                atom: url(Format::Atom),
+                rss: url(Format::Rss),
+                token,
+            },
+        }
+    }
+}
+
+#[get("/user/feed-token")]
+pub async fn get_feed_token(
+    auth: Auth,
+    site: web::Data<Site>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let token = web::block(move || {
+        let conn = pool.get().unwrap();
+        feed_token::get(&conn, auth.claims.id)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(FeedTokenResult::new(&site, token)))
+}
+
+/// Replaces the token, for when a personal feed URL leaked
+#[post("/user/feed-token")]
+pub async fn regenerate_feed_token(
+    auth: Auth,
+    site: web::Data<Site>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let token = web::block(move || {
+        let conn = pool.get().unwrap();
+        feed_token::regenerate(&conn, auth.claims.id)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(FeedTokenResult::new(&site, token)))
+}
@@ -0,0 +1,269 @@
+use crate::{
+    api::uploads::UploadConfig,
+    card::{CardRenderer, HEIGHT, WIDTH},
+    db::{self, article, Article},
+    errors::Errors,
+    storage,
+    Pool,
+};
+use actix_web::{
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE},
+        StatusCode,
+    },
+    web, Error, HttpResponse, Result,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Descriptions longer than this are cut short for previews.
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+
+/// Where the site is publicly reachable, configured through `HOSTNAME`.
+/// Links leaving the API, such as preview metadata, are built from it.
+#[derive(Debug, Clone)]
+pub struct Site {
+    url: String,
+}
+
+impl Site {
+    /// Serves `hostname` over https, unless it comes with its own scheme,
+    /// as in `http://localhost:8088`.
+    pub fn new(hostname: &str) -> Self {
+        let url = if hostname.contains("://") {
+            hostname.to_string()
+        } else {
+            format!("https://{}", hostname)
+        };
+        Site {
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// The absolute URL of `path`, which must start with a slash.
+    pub fn link(&self, path: &str) -> String {
+        format!("{}{}", self.url, path)
+    }
+
+    pub fn article_url(&self, slug: &str) -> String {
+        self.link(&format!("/article/{}", encode(slug)))
+    }
+
+    pub fn profile_url(&self, username: &str) -> String {
+        self.link(&format!("/profile/{}", encode(username)))
+    }
+
+    pub fn tag_url(&self, tag: &str) -> String {
+        self.link(&format!("/tag/{}", encode(tag)))
+    }
+
+    /// A `tag:` URI (RFC 4151) naming `specific` on this site for good,
+    /// minted on `date`.
+    pub fn tag_uri(&self, date: DateTime<Utc>, specific: &str) -> String {
+        let authority = self.url.split("://").nth(1).unwrap_or(&self.url);
+        let host = authority.split([':', '/']).next().unwrap_or("");
+        format!("tag:{},{}:{}", host, date.format("%Y-%m-%d"), specific)
+    }
+}
+
+/// Percent-encodes a path segment.
+pub(crate) fn encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// A `<meta>` tag. Open Graph tags go in its `property` attribute and
+/// Twitter card tags in its `name` attribute.
+#[derive(Serialize, Debug)]
+pub struct MetaTag {
+    name: &'static str,
+    content: String,
+}
+
+fn tag(name: &'static str, content: &str) -> MetaTag {
+    MetaTag {
+        name,
+        content: content.to_string(),
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Meta {
+    title: String,
+    description: String,
+    url: String,
+    image: String,
+    author: String,
+    author_url: String,
+    published_time: DateTime<Utc>,
+    modified_time: DateTime<Utc>,
+    tags: Vec<String>,
+    open_graph: Vec<MetaTag>,
+    twitter: Vec<MetaTag>,
+}
+
+#[derive(Serialize)]
+pub struct MetaResult {
+    meta: Meta,
+}
+
+fn summary(article: &Article) -> String {
+    let text = if article.description.trim().is_empty() {
+        &article.body
+    } else {
+        &article.description
+    };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= MAX_DESCRIPTION_LENGTH {
+        return text;
+    }
+    let mut short: String = text.chars().take(MAX_DESCRIPTION_LENGTH - 1).collect();
+    short.push('…');
+    short
+}
+
+impl Meta {
+    fn build(site: &Site, article: Article) -> Self {
+        let url = site.article_url(&article.slug);
+        let description = summary(&article);
+        let image = match article.cover_image {
+            Some(ref cover) if cover.starts_with('/') => site.link(cover),
+            Some(ref cover) => cover.clone(),
+            None => site.link(&format!(
+                "/api/articles/{}/card.png?v={}",
+                article.slug,
+                article.updated_at.timestamp()
+            )),
+        };
+        let author = article.author.username.clone();
+        let author_url = site.profile_url(&author);
+
+        let mut open_graph = vec![
+            tag("og:type", "article"),
+            tag("og:title", &article.title),
+            tag("og:description", &description),
+            tag("og:url", &url),
+            tag("og:image", &image),
+        ];
+        if article.cover_image.is_none() {
+            open_graph.push(tag("og:image:width", &WIDTH.to_string()));
+            open_graph.push(tag("og:image:height", &HEIGHT.to_string()));
+        }
+        open_graph.push(tag("article:author", &author_url));
+        open_graph.push(tag(
+            "article:published_time",
+            &article.created_at.to_rfc3339(),
+        ));
+        open_graph.push(tag(
+            "article:modified_time",
+            &article.updated_at.to_rfc3339(),
+        ));
+        for t in &article.tag_list {
+            open_graph.push(tag("article:tag", t));
+        }
+
+        let twitter = vec![
+            tag("twitter:card", "summary_large_image"),
+            tag("twitter:title", &article.title),
+            tag("twitter:description", &description),
+            tag("twitter:image", &image),
+        ];
+
+        Meta {
+            title: article.title,
+            description,
+            url,
+            image,
+            author,
+            author_url,
+            published_time: article.created_at,
+            modified_time: article.updated_at,
+            tags: article.tag_list,
+            open_graph,
+            twitter,
+        }
+    }
+}
+
+/// Open Graph and Twitter card metadata for sharing an article.
+#[get("/articles/{slug}/meta")]
+pub async fn article_meta(
+    info: web::Path<String>,
+    site: web::Data<Site>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        article::get_article(&conn, &slug, None)
+    })
+    .await
+    .map(|article| MetaResult {
+        meta: Meta::build(&site, article),
+    })
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// The social card shown for articles without a cover image. Cards are
+/// kept in the blob store and only drawn again once the article or its
+/// author changes.
+#[get("/articles/{slug}/card.png")]
+pub async fn article_card(
+    info: web::Path<String>,
+    cards: web::Data<CardRenderer>,
+    config: web::Data<UploadConfig>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let store = config.store.clone();
+    let data = web::block(move || {
+        let conn = pool.get().unwrap();
+        let article = article::get_article(&conn, &slug, None)?;
+        let author = &article.author;
+        let drawn_from = storage::key(
+            format!(
+                "{}\n{}\n{}\n{}",
+                article.slug,
+                article.title,
+                author.username,
+                author.image.as_deref().unwrap_or("")
+            )
+            .as_bytes(),
+        );
+        if let Some(hash) = db::card::find(&conn, article.id, &drawn_from)? {
+            match store.get(&hash) {
+                Ok(data) => return Ok(data),
+                Err(e) => warn!("failed to read card for {}: {}", article.slug, e),
+            }
+        }
+        let data = cards
+            .render(&article.slug, &article.title, &article.author.username)
+            .map_err(|e| {
+                error!("failed to render card for {}: {}", article.slug, e);
+                Errors::new().code(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+        match store.put(&data) {
+            Ok(hash) => {
+                db::card::save(&conn, article.id, &drawn_from, &hash)?;
+            }
+            Err(e) => error!("failed to store card for {}: {}", article.slug, e),
+        }
+        Ok::<_, Errors>(data)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok()
+        .header(CONTENT_TYPE, "image/png")
+        .header(CACHE_CONTROL, "public, max-age=86400")
+        .body(data))
+}
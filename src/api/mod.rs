@@ -1,3 +1,13 @@
+pub mod admin;
 pub mod articles;
+pub mod feeds;
+pub mod meta;
+pub mod moderation;
 pub mod profile;
+pub mod reactions;
+pub mod series;
+pub mod sitemaps;
+pub mod stats;
+pub mod tags;
+pub mod uploads;
 pub mod users;
@@ -0,0 +1,284 @@
+use crate::{
+    auth::Auth,
+    db::{
+        report::{self, NewReport, Report},
+        warning::{self, Warning},
+        Crud, Page, User,
+    },
+    errors::Errors,
+    filter::{Pipeline, Submission, Verdict},
+    Pool,
+};
+use actix_web::{http::StatusCode, web, Error, HttpResponse, Result};
+use diesel::{pg::PgConnection, result::Error as DieselError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Moderation settings, configured through `REPORT_HIDE_THRESHOLD`.
+#[derive(Clone)]
+pub struct ModerationConfig {
+    /// Distinct reporters after which an article or comment is hidden
+    /// pending review; zero never hides anything automatically.
+    pub hide_after: i64,
+}
+
+pub(crate) fn require_moderator(conn: &PgConnection, user_id: i32) -> Result<(), Errors> {
+    if User::read(conn, user_id)?.is_moderator() {
+        Ok(())
+    } else {
+        Err(Errors::new().code(StatusCode::FORBIDDEN))
+    }
+}
+
+/// Runs the content filters on a write. Rejections become errors, and held
+/// writes return the reason for the moderation queue.
+pub(crate) fn screen(
+    conn: &PgConnection,
+    filters: &Pipeline,
+    submission: &Submission,
+) -> Result<Option<String>, Errors> {
+    match filters.check(conn, submission)? {
+        Verdict::Allow => Ok(None),
+        Verdict::Hold(reason) => Ok(Some(reason)),
+        Verdict::Reject(field, reason) => Err(Errors::with_field(field, &reason)),
+    }
+}
+
+fn not_found(e: DieselError) -> Errors {
+    match e {
+        DieselError::NotFound => Errors::new().code(StatusCode::NOT_FOUND),
+        e => e.into(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewReportRequest {
+    report: NewReportData,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NewReportData {
+    target_type: String,
+    /// The slug of an article, the id of a comment or a username.
+    target_id: Value,
+    reason: String,
+    #[serde(default)]
+    details: String,
+}
+
+#[derive(Serialize)]
+pub struct ReportResult {
+    report: Report,
+}
+
+#[derive(Serialize)]
+pub struct ReportsResult {
+    reports: Vec<Report>,
+}
+
+#[post("/reports")]
+pub async fn create_report(
+    auth: Auth,
+    form: web::Json<NewReportRequest>,
+    config: web::Data<ModerationConfig>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let data = form.into_inner().report;
+    let mut errors = Errors::new();
+    if !report::TARGET_TYPES.contains(&data.target_type.as_str()) {
+        errors.insert_error("targetType", "must be article, comment or profile");
+    }
+    let key = match &data.target_id {
+        Value::String(key) => key.clone(),
+        Value::Number(id) => id.to_string(),
+        _ => {
+            errors.insert_error("targetId", "must be a slug, id or username");
+            String::new()
+        }
+    };
+    if !report::REASONS.contains(&data.reason.as_str()) {
+        errors.insert_error(
+            "reason",
+            &format!("must be one of {}", report::REASONS.join(", ")),
+        );
+    }
+    if data.details.chars().count() > 2000 {
+        errors.insert_error("details", "cannot be longer than 2000 characters");
+    }
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+
+    let user_id = auth.claims.id;
+    let hide_after = config.hide_after;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        let (target_id, owner) =
+            report::find_target(&conn, &data.target_type, &key).map_err(not_found)?;
+        if owner == user_id {
+            return Err(Errors::with_field("targetId", "cannot be your own"));
+        }
+        let new_report = NewReport {
+            reporter: Some(user_id),
+            target_type: &data.target_type,
+            target_id,
+            reason: &data.reason,
+            details: &data.details,
+        };
+        Ok(report::create(&conn, &new_report, hide_after)?)
+    })
+    .await
+    .map(|report| ReportResult { report })
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Created().json(result))
+}
+
+#[derive(Deserialize)]
+pub struct QueueQuery {
+    status: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+/// The moderation queue, open reports by default
+#[get("/moderation/reports")]
+pub async fn list_reports(
+    auth: Auth,
+    query: web::Query<QueueQuery>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    let status = query.status.unwrap_or_else(|| report::OPEN.to_string());
+    if !report::STATUSES.contains(&status.as_str()) {
+        return Err(Errors::with_field("status", "must be open, actioned or dismissed").into());
+    }
+    let page = Page {
+        limit: query.limit,
+        offset: query.offset,
+    };
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        require_moderator(&conn, auth.claims.id)?;
+        Ok::<_, Errors>(report::list(&conn, &status, &page)?)
+    })
+    .await
+    .map(|reports| ReportsResult { reports })
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/moderation/reports/{id}")]
+pub async fn get_report(
+    auth: Auth,
+    info: web::Path<i32>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let id = info.into_inner();
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        require_moderator(&conn, auth.claims.id)?;
+        report::get(&conn, id).map_err(not_found)
+    })
+    .await
+    .map(|report| ReportResult { report })
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[derive(Deserialize)]
+pub struct Resolution {
+    resolution: ResolutionData,
+}
+
+#[derive(Deserialize)]
+struct ResolutionData {
+    action: String,
+    /// Sent to the author as a warning; required to warn.
+    message: Option<String>,
+}
+
+/// Settles a report, and every other open report on the same target
+#[post("/moderation/reports/{id}/resolve")]
+pub async fn resolve_report(
+    auth: Auth,
+    info: web::Path<i32>,
+    form: web::Json<Resolution>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let id = info.into_inner();
+    let data = form.into_inner().resolution;
+    let action = data.action;
+    let message = data.message.filter(|m| !m.trim().is_empty());
+    if !report::ACTIONS.contains(&action.as_str()) {
+        let message = format!("must be one of {}", report::ACTIONS.join(", "));
+        return Err(Errors::with_field("action", &message).into());
+    }
+    if action == report::WARN && message.is_none() {
+        return Err(Errors::with_field("message", "is required to warn").into());
+    }
+
+    let moderator = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        require_moderator(&conn, moderator)?;
+        let current = report::get(&conn, id).map_err(not_found)?;
+        if current.status != report::OPEN {
+            return Err(Errors::with_field("report", "is already resolved"));
+        }
+        let hides = action == report::HIDE || action == report::REMOVE;
+        if hides && current.target_type == report::PROFILE {
+            return Err(Errors::with_field(
+                "action",
+                "cannot hide or remove a profile",
+            ));
+        }
+        Ok(report::resolve(
+            &conn,
+            id,
+            moderator,
+            &action,
+            message.as_deref(),
+        )?)
+    })
+    .await
+    .map(|report| ReportResult { report })
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[delete("/moderation/users/{username}/suspension")]
+pub async fn unsuspend(
+    auth: Auth,
+    info: web::Path<String>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let username = info.into_inner();
+    web::block(move || {
+        let conn = pool.get().unwrap();
+        require_moderator(&conn, auth.claims.id)?;
+        let user = User::with_username(&conn, &username).map_err(not_found)?;
+        Ok::<_, Errors>(User::set_suspended(&conn, user.id, false)?)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[derive(Serialize)]
+pub struct WarningsResult {
+    warnings: Vec<Warning>,
+}
+
+/// Warnings moderators sent the current user
+#[get("/user/warnings")]
+pub async fn warnings(auth: Auth, pool: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        warning::list(&conn, auth.claims.id)
+    })
+    .await
+    .map(|warnings| WarningsResult { warnings })
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
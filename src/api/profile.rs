@@ -1,5 +1,17 @@
-use crate::{auth::Auth, db::*, errors::Errors, Pool};
-use actix_web::{web, Error, HttpResponse, Result};
+use crate::{
+    auth::Auth,
+    avatar::{self, Format, SIZES},
+    db::*,
+    errors::Errors,
+    Pool,
+};
+use actix_web::{
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE},
+        StatusCode,
+    },
+    web, Error, HttpResponse, Result,
+};
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize)]
@@ -71,3 +83,57 @@ pub async fn unfollow(
     .map_err(Errors::from)?;
     Ok(HttpResponse::Ok().json(profile))
 }
+
+#[get("/profiles/{username}/favorites")]
+pub async fn favorites(
+    info: web::Path<String>,
+    query: web::Query<Page>,
+    auth: Option<Auth>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let username = info.into_inner();
+    let user_id = auth.map(|auth| auth.claims.id);
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        article::favorited_by(&conn, &username, &query, user_id)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[derive(Deserialize)]
+pub struct IdenticonQuery {
+    size: Option<u32>,
+    format: Option<String>,
+}
+
+/// The avatar of users who never uploaded one.
+#[get("/identicons/{seed}")]
+pub async fn identicon(
+    info: web::Path<String>,
+    query: web::Query<IdenticonQuery>,
+) -> Result<HttpResponse, Error> {
+    let seed = info.into_inner();
+    let size = query.size.unwrap_or(SIZES[1]);
+    if !SIZES.contains(&size) {
+        let sizes: Vec<String> = SIZES.iter().map(u32::to_string).collect();
+        return Err(Errors::with_field("size", &format!("must be one of {}", sizes.join(", "))).into());
+    }
+    let format = match query.format.as_deref() {
+        None => Format::Png,
+        Some(name) => Format::parse(name)
+            .ok_or_else(|| Errors::with_field("format", "must be png or webp"))?,
+    };
+
+    let data = web::block(move || avatar::identicon(&seed, size, format))
+        .await
+        .map_err(|e| {
+            error!("failed to draw identicon: {}", e);
+            Errors::new().code(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+    Ok(HttpResponse::Ok()
+        .header(CONTENT_TYPE, format.content_type())
+        .header(CACHE_CONTROL, "public, max-age=31536000, immutable")
+        .body(data))
+}
@@ -0,0 +1,86 @@
+use crate::{
+    api::articles::{ArticleResult, CommentResult},
+    auth::Auth,
+    db::{article, comment, reaction},
+    errors::Errors,
+    Pool,
+};
+use actix_web::{web, Error, HttpResponse, Result};
+use serde::Deserialize;
+
+/// The reactions users may leave, configured through `REACTIONS`.
+#[derive(Debug, Clone)]
+pub struct Reactions {
+    allowed: Vec<String>,
+}
+
+impl Reactions {
+    pub fn new(allowed: Vec<String>) -> Self {
+        Reactions { allowed }
+    }
+
+    fn check(&self, reaction: &str) -> Result<(), Errors> {
+        if self.allowed.iter().any(|r| r == reaction) {
+            return Ok(());
+        }
+        let mut errors = Errors::new();
+        errors.push_error(
+            "reaction",
+            &format!("must be one of {}", self.allowed.join(" ")),
+        );
+        Err(errors)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NewReaction {
+    reaction: String,
+}
+
+#[post("/articles/{slug}/reactions")]
+pub async fn react_article(
+    info: web::Path<String>,
+    body: web::Json<NewReaction>,
+    auth: Auth,
+    reactions: web::Data<Reactions>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let reaction = body.into_inner().reaction;
+    reactions.check(&reaction)?;
+
+    let user_id = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        reaction::toggle_article(&conn, user_id, &slug, &reaction)?;
+        article::get_article(&conn, &slug, Some(user_id))
+    })
+    .await
+    .map(ArticleResult::new)
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/articles/{slug}/comments/{id}/reactions")]
+pub async fn react_comment(
+    info: web::Path<(String, i32)>,
+    body: web::Json<NewReaction>,
+    auth: Auth,
+    reactions: web::Data<Reactions>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let (slug, comment_id) = info.into_inner();
+    let reaction = body.into_inner().reaction;
+    reactions.check(&reaction)?;
+
+    let user_id = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        reaction::toggle_comment(&conn, user_id, &slug, comment_id, &reaction)?;
+        comment::get_comment(&conn, Some(user_id), &slug, comment_id)
+    })
+    .await
+    .map(CommentResult::new)
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
@@ -0,0 +1,208 @@
+use crate::{
+    auth::Auth,
+    db::{
+        series::{self, Series, SeriesForm, SeriesUpdateData},
+        Page,
+    },
+    errors::Errors,
+    validation::{validate_length, MAX_DESCRIPTION_LENGTH, MAX_TITLE_LENGTH},
+    Pool,
+};
+use actix_web::{http::StatusCode, web, Error, HttpResponse, Result};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Deserialize, Debug)]
+pub struct NewSeries {
+    series: NewSeriesData,
+}
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct NewSeriesData {
+    #[validate(length(min = 1, message = "title cannot be empty"))]
+    title: String,
+    description: Option<String>,
+    articles: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpdateSeries {
+    series: UpdateSeriesData,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpdateSeriesData {
+    title: Option<String>,
+    description: Option<String>,
+    articles: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SeriesQuery {
+    owner: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct SeriesResult {
+    series: Series,
+}
+
+impl SeriesResult {
+    pub fn new(series: Series) -> Self {
+        SeriesResult { series }
+    }
+}
+
+#[get("/series")]
+pub async fn list_series(
+    query: web::Query<SeriesQuery>,
+    auth: Option<Auth>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    let page = Page {
+        limit: query.limit,
+        offset: query.offset,
+    };
+    let user_id = auth.map(|a| a.claims.id);
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        series::list(&conn, query.owner.as_deref(), &page, user_id)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/series/{slug}")]
+pub async fn get_series(
+    info: web::Path<String>,
+    auth: Option<Auth>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let user_id = auth.map(|a| a.claims.id);
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        series::get(&conn, &slug, user_id)
+    })
+    .await
+    .map(SeriesResult::new)
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Refuses a list of article slugs naming the same article twice, since it
+/// could only take one position.
+fn validate_articles(errors: &mut Errors, articles: Option<&[String]>) {
+    let articles = articles.unwrap_or_default();
+    if articles.iter().enumerate().any(|(i, slug)| articles[..i].contains(slug)) {
+        errors.push_error("articles", "cannot list an article twice");
+    }
+}
+
+#[post("/series")]
+pub async fn create_series(
+    new_series: web::Json<NewSeries>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let data = new_series.into_inner().series;
+    data.validate().map_err(Errors::from)?;
+    let mut errors = Errors::new();
+    validate_length(&mut errors, "title", &data.title, MAX_TITLE_LENGTH);
+    if let Some(ref description) = data.description {
+        validate_length(&mut errors, "description", description, MAX_DESCRIPTION_LENGTH);
+    }
+    validate_articles(&mut errors, data.articles.as_deref());
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+
+    let mut form = SeriesForm {
+        slug: String::new(),
+        title: data.title,
+        description: data.description.unwrap_or_default(),
+        owner: auth.claims.id,
+    };
+    let articles = data.articles.unwrap_or_default();
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        let title = form.title.clone();
+        series::with_unique_slug(&conn, &title, None, |slug| {
+            form.slug = slug;
+            series::create(&conn, &form, &articles)
+        })
+    })
+    .await
+    .map(SeriesResult::new)
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[put("/series/{slug}")]
+pub async fn update_series(
+    info: web::Path<String>,
+    update: web::Json<UpdateSeries>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let data = update.into_inner().series;
+    let mut errors = Errors::new();
+    if let Some(ref title) = data.title {
+        if title.is_empty() {
+            errors.push_error("title", "title cannot be empty");
+        }
+        validate_length(&mut errors, "title", title, MAX_TITLE_LENGTH);
+    }
+    if let Some(ref description) = data.description {
+        validate_length(&mut errors, "description", description, MAX_DESCRIPTION_LENGTH);
+    }
+    validate_articles(&mut errors, data.articles.as_deref());
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+
+    let user_id = auth.claims.id;
+    let mut changes = SeriesUpdateData {
+        slug: None,
+        title: data.title,
+        description: data.description,
+    };
+    let articles = data.articles;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        match changes.title.clone() {
+            Some(title) => series::with_unique_slug(&conn, &title, Some(&slug), |new_slug| {
+                changes.slug = Some(new_slug);
+                series::update(&conn, &slug, user_id, &changes, articles.as_deref())
+            }),
+            None => series::update(&conn, &slug, user_id, &changes, articles.as_deref()),
+        }
+    })
+    .await
+    .map(SeriesResult::new)
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[delete("/series/{slug}")]
+pub async fn delete_series(
+    info: web::Path<String>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let slug = info.into_inner();
+    let user_id = auth.claims.id;
+    web::block(move || {
+        let conn = pool.get().unwrap();
+        series::delete(&conn, &slug, user_id)
+    })
+    .await
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::new(StatusCode::OK))
+}
@@ -0,0 +1,88 @@
+use crate::{
+    api::meta::Site,
+    db::sitemap::{self, PAGE_SIZE},
+    errors::Errors,
+    feed::escape,
+    Pool,
+};
+use actix_web::{
+    http::{header::CONTENT_TYPE, StatusCode},
+    web, Error, HttpResponse, Result,
+};
+use std::fmt::Write;
+
+const CONTENT_TYPE_XML: &str = "application/xml; charset=utf-8";
+
+/// The sitemap index, pointing at one sitemap per `PAGE_SIZE` entries
+#[get("/sitemap.xml")]
+pub async fn sitemap_index(
+    site: web::Data<Site>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let pages = web::block(move || {
+        let conn = pool.get().unwrap();
+        sitemap::pages(&conn)
+    })
+    .await
+    .map_err(Errors::from)?;
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for page in pages {
+        let loc = site.link(&format!("/sitemaps/{}.xml", page.page));
+        let _ = writeln!(
+            xml,
+            "  <sitemap><loc>{}</loc><lastmod>{}</lastmod></sitemap>",
+            escape(&loc),
+            page.lastmod.to_rfc3339()
+        );
+    }
+    xml.push_str("</sitemapindex>\n");
+    Ok(HttpResponse::Ok()
+        .header(CONTENT_TYPE, CONTENT_TYPE_XML)
+        .body(xml))
+}
+
+#[get("/sitemaps/{page}.xml")]
+pub async fn sitemap_page(
+    info: web::Path<i64>,
+    site: web::Data<Site>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let page = info.into_inner();
+    if page < 0 || page > i64::from(i32::MAX) / PAGE_SIZE {
+        return Err(Errors::new().code(StatusCode::NOT_FOUND).into());
+    }
+    let entries = web::block(move || {
+        let conn = pool.get().unwrap();
+        sitemap::entries(&conn, page)
+    })
+    .await
+    .map_err(Errors::from)?;
+    if entries.is_empty() {
+        return Err(Errors::new().code(StatusCode::NOT_FOUND).into());
+    }
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for entry in entries {
+        let loc = match entry.kind.as_str() {
+            sitemap::ARTICLE => site.article_url(&entry.name),
+            sitemap::PROFILE => site.profile_url(&entry.name),
+            sitemap::TAG => site.tag_url(&entry.name),
+            _ => continue,
+        };
+        let _ = writeln!(
+            xml,
+            "  <url><loc>{}</loc><lastmod>{}</lastmod></url>",
+            escape(&loc),
+            entry.lastmod.to_rfc3339()
+        );
+    }
+    xml.push_str("</urlset>\n");
+    Ok(HttpResponse::Ok()
+        .header(CONTENT_TYPE, CONTENT_TYPE_XML)
+        .body(xml))
+}
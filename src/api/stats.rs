@@ -0,0 +1,48 @@
+use crate::{auth::Auth, db::stats, errors::Errors, Pool};
+use actix_web::{web, Error, HttpResponse, Result};
+use serde::{Deserialize, Serialize};
+
+/// Stats reach back at most this far, which also keeps the date arithmetic
+/// in range.
+const MAX_DAYS: i64 = 5 * 365;
+
+#[derive(Deserialize, Debug)]
+pub struct StatsQuery {
+    bucket: Option<String>,
+    days: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct StatsResult {
+    stats: stats::Stats,
+}
+
+#[get("/user/stats")]
+pub async fn user_stats(
+    query: web::Query<StatsQuery>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    let bucket = query.bucket.unwrap_or_else(|| "day".to_string());
+    if !["day", "week", "month"].contains(&bucket.as_str()) {
+        return Err(Errors::with_field("bucket", "must be day, week or month").into());
+    }
+    let days = query.days.unwrap_or(30);
+    if !(1..=MAX_DAYS).contains(&days) {
+        return Err(
+            Errors::with_field("days", &format!("must be between 1 and {}", MAX_DAYS)).into(),
+        );
+    }
+    let author = auth.claims.id;
+
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        stats::author_stats(&conn, author, &bucket, days)
+    })
+    .await
+    .map(|stats| StatsResult { stats })
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
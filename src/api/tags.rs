@@ -0,0 +1,212 @@
+use crate::{
+    api::moderation::require_moderator,
+    auth::Auth,
+    db::{tag, Tag},
+    errors::Errors,
+    validation::{normalize_tags, validate_tags},
+    Pool,
+};
+use actix_web::{web, Error, HttpResponse, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const MAX_TRENDING_DAYS: i64 = 365;
+const MAX_TRENDING_LIMIT: i64 = 100;
+const MAX_DESCRIPTION_LENGTH: usize = 500;
+
+#[derive(Serialize, Deserialize)]
+pub struct TagsResult {
+    tags: Vec<String>
+}
+
+/// Tag names in order of popularity, and how many articles each has.
+#[derive(Serialize, Deserialize)]
+pub struct PopularResult {
+    tags: Vec<String>,
+    counts: HashMap<String, i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TrendingResult {
+    tags: Vec<Tag>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TagResult {
+    tag: Tag,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TrendingQuery {
+    days: Option<i64>,
+    limit: Option<i64>,
+}
+
+#[get("/tags")]
+pub async fn tags(pool: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        tag::popular(&conn)
+    })
+    .await
+    .map(|popular| PopularResult {
+        tags: popular.iter().map(|(name, _)| name.clone()).collect(),
+        counts: popular.into_iter().collect(),
+    })
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/tags/trending")]
+pub async fn trending(
+    query: web::Query<TrendingQuery>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let days = query.days.unwrap_or(7);
+    let limit = query.limit.unwrap_or(10);
+    let mut errors = Errors::new();
+    if !(1..=MAX_TRENDING_DAYS).contains(&days) {
+        errors.insert_error(
+            "days",
+            &format!("must be between 1 and {}", MAX_TRENDING_DAYS),
+        );
+    }
+    if !(1..=MAX_TRENDING_LIMIT).contains(&limit) {
+        errors.insert_error(
+            "limit",
+            &format!("must be between 1 and {}", MAX_TRENDING_LIMIT),
+        );
+    }
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        tag::trending(&conn, days, limit)
+    })
+    .await
+    .map(|list| TrendingResult { tags: list })
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/tags/{tag}")]
+pub async fn get_tag(info: web::Path<String>, pool: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    let name = info.into_inner();
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        tag::find(&conn, &name)
+    })
+    .await
+    .map(|tag| TagResult { tag })
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTag {
+    tag: UpdateTagData,
+}
+
+#[derive(Deserialize)]
+struct UpdateTagData {
+    /// An empty or missing description clears it.
+    description: Option<String>,
+}
+
+/// Describes a tag, for moderators
+#[put("/tags/{tag}")]
+pub async fn update_tag(
+    info: web::Path<String>,
+    auth: Auth,
+    form: web::Json<UpdateTag>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let name = info.into_inner();
+    let description = form
+        .into_inner()
+        .tag
+        .description
+        .map(|d| d.trim().to_owned())
+        .filter(|d| !d.is_empty());
+    if description
+        .as_ref()
+        .is_some_and(|d| d.chars().count() > MAX_DESCRIPTION_LENGTH)
+    {
+        let message = format!("cannot be longer than {} characters", MAX_DESCRIPTION_LENGTH);
+        return Err(Errors::with_field("description", &message).into());
+    }
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        require_moderator(&conn, auth.claims.id)?;
+        Ok::<_, Errors>(tag::describe(&conn, &name, description.as_deref())?)
+    })
+    .await
+    .map(|tag| TagResult { tag })
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/tags/{tag}/follow")]
+pub async fn follow(
+    info: web::Path<String>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let name = match normalize_tags(vec![info.into_inner()]).pop() {
+        Some(name) => name,
+        None => return Err(Errors::with_field("tag", "cannot be empty").into()),
+    };
+    let mut errors = Errors::new();
+    validate_tags(&mut errors, "tag", std::slice::from_ref(&name));
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    let follower = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        tag::follow(&conn, &name, follower)
+    })
+    .await
+    .map(|tag| TagResult { tag })
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[delete("/tags/{tag}/follow")]
+pub async fn unfollow(
+    info: web::Path<String>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let name = info.into_inner();
+    let follower = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        tag::unfollow(&conn, &name, follower)
+    })
+    .await
+    .map(|tag| TagResult { tag })
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/user/tags")]
+pub async fn followed(auth: Auth, pool: web::Data<Pool>) -> Result<HttpResponse, Error> {
+    let follower = auth.claims.id;
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        tag::followed(&conn, follower)
+    })
+    .await
+    .map(|list| TagsResult { tags: list })
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
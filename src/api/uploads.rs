@@ -0,0 +1,212 @@
+use crate::{
+    auth::Auth,
+    db::{upload, Page},
+    errors::Errors,
+    storage::{self, BlobStore},
+    Pool,
+};
+use actix_multipart::Multipart;
+use actix_web::{
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        StatusCode,
+    },
+    web, Error, HttpRequest, HttpResponse, Result,
+};
+use diesel::Connection;
+use futures::StreamExt;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Upload storage and limits, configured through `UPLOAD_*` settings.
+#[derive(Clone)]
+pub struct UploadConfig {
+    pub store: Arc<dyn BlobStore>,
+    pub max_size: usize,
+    pub quota: i64,
+}
+
+#[derive(Serialize)]
+pub struct UploadResult {
+    upload: upload::Upload,
+}
+
+/// Reads the `file` field of a multipart body, rejecting it once it grows
+/// past `max_size`.
+pub(crate) async fn read_file(
+    mut payload: Multipart,
+    max_size: usize,
+) -> Result<Option<(Vec<u8>, Option<String>)>, Error> {
+    let mut file = None;
+    while let Some(field) = payload.next().await {
+        let mut field = field?;
+        let disposition = field.content_disposition();
+        let is_file = disposition.as_ref().and_then(|d| d.get_name()) == Some("file");
+        let filename = disposition
+            .as_ref()
+            .and_then(|d| d.get_filename())
+            .map(str::to_owned);
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk?;
+            if !is_file {
+                continue;
+            }
+            if data.len() + chunk.len() > max_size {
+                let message = format!("cannot be larger than {} bytes", max_size);
+                return Err(Errors::with_field("file", &message)
+                    .code(StatusCode::PAYLOAD_TOO_LARGE)
+                    .into());
+            }
+            data.extend_from_slice(&chunk);
+        }
+        if is_file && file.is_none() {
+            file = Some((data, filename));
+        }
+    }
+    Ok(file)
+}
+
+#[post("/uploads")]
+pub async fn create_upload(
+    payload: Multipart,
+    auth: Auth,
+    config: web::Data<UploadConfig>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let (data, filename) = read_file(payload, config.max_size)
+        .await?
+        .ok_or_else(|| Errors::with_field("file", "is missing"))?;
+    let content_type = storage::sniff(&data).ok_or_else(|| {
+        Errors::with_field("file", "must be a PNG, JPEG, GIF, WebP or PDF file")
+            .code(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+    })?;
+
+    let user_id = auth.claims.id;
+    let quota = config.quota;
+    let store = config.store.clone();
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        let size = data.len() as i64;
+        conn.transaction(|| {
+            upload::lock_owner(&conn, user_id)?;
+            if !upload::fits(&conn, user_id, &[(storage::key(&data), size)], quota)? {
+                return Err(Errors::with_field("file", "upload quota exceeded")
+                    .code(StatusCode::PAYLOAD_TOO_LARGE));
+            }
+            let hash = store.put(&data).map_err(|e| {
+                error!("failed to store upload: {}", e);
+                Errors::with_field("file", "could not be stored")
+                    .code(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+            upload::create(
+                &conn,
+                user_id,
+                &hash,
+                content_type,
+                size,
+                filename.as_deref(),
+            )
+            .map_err(Errors::from)
+        })
+    })
+    .await
+    .map(|upload| UploadResult { upload })
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/uploads/{hash}")]
+pub async fn get_upload(
+    req: HttpRequest,
+    info: web::Path<String>,
+    config: web::Data<UploadConfig>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let hash = info.into_inner();
+    if !storage::is_key(&hash) {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let etag = format!("\"{}\"", hash);
+    let not_modified = req
+        .headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|t| t.trim() == etag));
+
+    let store = config.store.clone();
+    let (content_type, data) = web::block(move || {
+        let conn = pool.get().unwrap();
+        let content_type = upload::content_type(&conn, &hash).map_err(Errors::from)?;
+        if not_modified {
+            return Ok::<_, Errors>((content_type, Vec::new()));
+        }
+        let data = store.get(&hash).map_err(|e| {
+            error!("failed to read upload {}: {}", hash, e);
+            Errors::with_field("file", "is missing").code(StatusCode::NOT_FOUND)
+        })?;
+        Ok((content_type, data))
+    })
+    .await
+    .map_err(|e| Errors::from(e).code(StatusCode::NOT_FOUND))?;
+
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .header(ETAG, etag)
+        .header(CACHE_CONTROL, "public, max-age=31536000, immutable")
+        .header("X-Content-Type-Options", "nosniff");
+    if not_modified {
+        return Ok(response.finish());
+    }
+    Ok(response.header(CONTENT_TYPE, content_type).body(data))
+}
+
+#[get("/user/uploads")]
+pub async fn list_uploads(
+    query: web::Query<Page>,
+    auth: Auth,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        upload::list(&conn, auth.claims.id, &query)
+    })
+    .await
+    .map_err(Errors::from)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[delete("/uploads/{hash}")]
+pub async fn delete_upload(
+    info: web::Path<String>,
+    auth: Auth,
+    config: web::Data<UploadConfig>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let hash = info.into_inner();
+    let user_id = auth.claims.id;
+    let store = config.store.clone();
+    web::block(move || {
+        let conn = pool.get().unwrap();
+        if upload::delete(&conn, user_id, &hash)? == 0 {
+            if upload::is_owned(&conn, user_id, &hash)? {
+                return Err(Errors::with_field("hash", "is still in use")
+                    .code(StatusCode::CONFLICT));
+            }
+        } else if !upload::is_stored(&conn, &hash)? {
+            if let Err(e) = store.delete(&hash) {
+                error!("failed to delete upload {}: {}", hash, e);
+            }
+        }
+        Ok(())
+    })
+    .await
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::new(StatusCode::OK))
+}
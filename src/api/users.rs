@@ -1,14 +1,19 @@
-use actix_web::{web, Error, HttpResponse, Result};
+use actix_multipart::Multipart;
+use actix_web::{http::StatusCode, web, Error, HttpResponse, Result};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use serde::{ Serialize, Deserialize };
 use validator::Validate;
 
 use crate::{
+    api::{profile::ProfileResult, uploads::{read_file, UploadConfig}},
     auth::Auth,
-    db::{Crud, User, UserForm},
+    avatar,
+    db::{upload, Avatar, Crud, User, UserForm},
     errors::Errors,
+    storage::{self, BlobStore},
     Pool,
 };
+use diesel::{pg::PgConnection, Connection};
 
 #[derive(Deserialize)]
 pub struct NewUser {
@@ -107,6 +112,9 @@ pub(crate) async fn login(
     if !valid {
         return Err(Errors::with_field("password", "incorrectly"))?;
     }
+    if db_user.is_suspended() {
+        return Err(Errors::with_field("user", "is suspended").code(StatusCode::FORBIDDEN).into());
+    }
 
     let jwt = db_user.jwt("secret");
     db_user.token = jwt;
@@ -150,3 +158,112 @@ pub(crate) async fn put_user(
 
     Ok(HttpResponse::Ok().json(user))
 }
+
+/// Replaces the avatar with a processed copy of the uploaded image.
+#[post("/user/avatar")]
+pub(crate) async fn upload_avatar(
+    payload: Multipart,
+    auth: Auth,
+    config: web::Data<UploadConfig>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let (data, _) = read_file(payload, config.max_size)
+        .await?
+        .ok_or_else(|| Errors::with_field("file", "is missing"))?;
+    if !storage::sniff(&data).is_some_and(|t| t.starts_with("image/")) {
+        return Err(Errors::with_field("file", "must be a PNG, JPEG, GIF or WebP image")
+            .code(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+            .into());
+    }
+
+    let user_id = auth.claims.id;
+    let quota = config.quota;
+    let store = config.store.clone();
+    let profile = web::block(move || {
+        let renditions = avatar::process(&data)
+            .map_err(|_| Errors::with_field("file", "could not be read as an image"))?;
+        let conn = pool.get().unwrap();
+        let files: Vec<(String, i64)> = renditions
+            .iter()
+            .map(|r| (storage::key(&r.data), r.data.len() as i64))
+            .collect();
+        let (user, unheld) = conn.transaction(|| {
+            upload::lock_owner(&conn, user_id)?;
+            if !upload::fits(&conn, user_id, &files, quota)? {
+                return Err(Errors::with_field("file", "upload quota exceeded")
+                    .code(StatusCode::PAYLOAD_TOO_LARGE));
+            }
+
+            let mut hashes = Avatar::new();
+            for rendition in renditions {
+                let hash = store.put(&rendition.data).map_err(|e| {
+                    error!("failed to store avatar: {}", e);
+                    Errors::with_field("file", "could not be stored")
+                        .code(StatusCode::INTERNAL_SERVER_ERROR)
+                })?;
+                let filename = format!("avatar-{}.{}", rendition.size, rendition.format.name());
+                let size = rendition.data.len() as i64;
+                upload::create(&conn, user_id, &hash, rendition.format.content_type(), size, Some(&filename))?;
+                hashes
+                    .entry(rendition.size)
+                    .or_default()
+                    .insert(rendition.format.name().to_string(), hash);
+            }
+            replace_avatar(&conn, user_id, Some(&hashes)).map_err(Errors::from)
+        })?;
+        delete_blobs(&*store, &unheld);
+        Ok::<_, Errors>(user.to_profile(false))
+    })
+    .await
+    .map(ProfileResult::new)
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+/// Goes back to the identicon.
+#[delete("/user/avatar")]
+pub(crate) async fn delete_avatar(
+    auth: Auth,
+    config: web::Data<UploadConfig>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, Error> {
+    let user_id = auth.claims.id;
+    let store = config.store.clone();
+    let profile = web::block(move || {
+        let conn = pool.get().unwrap();
+        let (user, unheld) = conn.transaction(|| replace_avatar(&conn, user_id, None))?;
+        delete_blobs(&*store, &unheld);
+        Ok::<_, diesel::result::Error>(user.to_profile(false))
+    })
+    .await
+    .map(ProfileResult::new)
+    .map_err(Errors::from)?;
+
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+/// Sets the avatar of `user_id` and releases the uploads of the renditions
+/// it replaces, returning the hashes of those no one holds any more.
+fn replace_avatar(
+    conn: &PgConnection,
+    user_id: i32,
+    hashes: Option<&Avatar>,
+) -> Result<(User, Vec<String>), diesel::result::Error> {
+    let old = User::read(conn, user_id)?.avatar_hashes().unwrap_or_default();
+    let user = User::set_avatar(conn, user_id, hashes)?;
+    let replaced: Vec<String> = old
+        .values()
+        .flat_map(|formats| formats.values().cloned())
+        .collect();
+    let unheld = upload::release(conn, user_id, &replaced)?;
+    Ok((user, unheld))
+}
+
+fn delete_blobs(store: &dyn BlobStore, hashes: &[String]) {
+    for hash in hashes {
+        if let Err(e) = store.delete(hash) {
+            error!("failed to delete upload {}: {}", hash, e);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// Limit on the size of a gzipped archive once unpacked.
+pub const MAX_UNPACKED_SIZE: u64 = 256 * 1024 * 1024;
+
+/// The YAML front matter heading an exported article.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FrontMatter {
+    pub title: String,
+    /// Taken from the file name when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    /// Username of the author.
+    pub author: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// An article as a Markdown file with YAML front matter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub front: FrontMatter,
+    pub body: String,
+}
+
+/// A file read from an archive, with the reason it could not be parsed.
+pub type Entry = (String, Result<Document, String>);
+
+const DELIMITER: &str = "---\n";
+
+impl Document {
+    pub fn file_name(&self) -> String {
+        format!("{}.md", self.front.slug.as_deref().unwrap_or("article"))
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let front = serde_yaml::to_string(&self.front).unwrap();
+        format!("{}{}{}\n{}", DELIMITER, front, DELIMITER, self.body)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.replace("\r\n", "\n");
+        let rest = text.strip_prefix(DELIMITER).ok_or("missing front matter")?;
+        let end = rest.find("\n---\n").ok_or("front matter is not closed")?;
+        let front = serde_yaml::from_str(&rest[..end + 1]).map_err(|e| e.to_string())?;
+        let body = &rest[end + 1 + DELIMITER.len()..];
+        let body = body.strip_prefix('\n').unwrap_or(body);
+        Ok(Document {
+            front,
+            body: body.to_string(),
+        })
+    }
+}
+
+fn is_gzip(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+fn is_tar(path: &Path) -> bool {
+    is_gzip(path) || path.to_string_lossy().ends_with(".tar")
+}
+
+/// Writes `documents` to a `.tar`, `.tar.gz` or `.tgz` file, or to a
+/// directory for any other path.
+pub fn write(path: &Path, documents: &[Document]) -> io::Result<()> {
+    if !is_tar(path) {
+        fs::create_dir_all(path)?;
+        for document in documents {
+            fs::write(path.join(document.file_name()), document.to_markdown())?;
+        }
+        return Ok(());
+    }
+    let file = fs::File::create(path)?;
+    if is_gzip(path) {
+        write_tar(GzEncoder::new(file, Compression::default()), documents)?.finish()?;
+    } else {
+        write_tar(file, documents)?;
+    }
+    Ok(())
+}
+
+/// Writes `documents` as a tarball, gzipped by the caller if wanted.
+pub fn write_tar<W: Write>(writer: W, documents: &[Document]) -> io::Result<W> {
+    let mut tar = tar::Builder::new(writer);
+    for document in documents {
+        let data = document.to_markdown();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        let modified = document.front.updated_at.or(document.front.created_at);
+        header.set_mtime(modified.map_or(0, |t| t.timestamp().max(0) as u64));
+        tar.append_data(&mut header, document.file_name(), data.as_bytes())?;
+    }
+    tar.into_inner()
+}
+
+/// Reads every `.md` file of a directory or tarball written by `write`.
+pub fn read(path: &Path) -> io::Result<Vec<Entry>> {
+    if is_tar(path) {
+        return read_tar(fs::File::open(path)?);
+    }
+    let mut names = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if name.ends_with(".md") {
+            names.push(name);
+        }
+    }
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let text = fs::read_to_string(path.join(&name))?;
+            let document = Document::parse(&text);
+            Ok((name, document))
+        })
+        .collect()
+}
+
+/// Reads the `.md` files of a tarball, gzipped or not.
+pub fn read_tar<R: Read>(mut reader: R) -> io::Result<Vec<Entry>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    if data.starts_with(&[0x1f, 0x8b]) {
+        let mut plain = Vec::new();
+        GzDecoder::new(&data[..])
+            .take(MAX_UNPACKED_SIZE + 1)
+            .read_to_end(&mut plain)?;
+        if plain.len() as u64 > MAX_UNPACKED_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unpacks to more than {} bytes", MAX_UNPACKED_SIZE),
+            ));
+        }
+        data = plain;
+    }
+
+    let mut entries = Vec::new();
+    let mut tar = tar::Archive::new(&data[..]);
+    for file in tar.entries()? {
+        let mut file = file?;
+        if !file.header().entry_type().is_file() {
+            continue;
+        }
+        let path = file.path()?.to_string_lossy().into_owned();
+        if !path.ends_with(".md") {
+            continue;
+        }
+        let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+        let mut text = String::new();
+        let document = match file.read_to_string(&mut text) {
+            Ok(_) => Document::parse(&text),
+            Err(_) => Err("is not valid UTF-8".to_string()),
+        };
+        entries.push((name, document));
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
@@ -1,8 +1,9 @@
 extern crate jsonwebtoken as jwt;
+use crate::{db::User, errors::Errors, Pool};
 use actix_web::error::ErrorUnauthorized;
-use actix_web::http::header::AUTHORIZATION;
-use actix_web::{dev, Error, FromRequest, HttpRequest};
-use futures::future::{err, ok, Ready};
+use actix_web::http::{header::AUTHORIZATION, StatusCode};
+use actix_web::{dev, web, Error, FromRequest, HttpRequest};
+use futures::future::{err, ok, LocalBoxFuture};
 use jwt::{decode, Validation};
 use serde::{Deserialize, Serialize};
 
@@ -23,13 +24,44 @@ pub struct Auth {
 
 impl FromRequest for Auth {
     type Error = Error;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
     type Config = ();
 
+    /// Reads the token, and for anything but reads also checks that the
+    /// user is not suspended, as their tokens stay valid until they expire.
     fn from_request(req: &HttpRequest, _payload: &mut dev::Payload) -> Self::Future {
+        let auth = match Auth::from_headers(req) {
+            Ok(auth) => auth,
+            Err(e) => return Box::pin(err(e)),
+        };
+        if req.method().is_safe() {
+            return Box::pin(ok(auth));
+        }
+        let pool = req.app_data::<web::Data<Pool>>().cloned();
+        Box::pin(async move {
+            let pool = pool.ok_or_else(|| ErrorUnauthorized("error unauthorized"))?;
+            let id = auth.claims.id;
+            let suspended = web::block(move || {
+                let conn = pool.get().unwrap();
+                User::is_suspended_id(&conn, id)
+            })
+            .await
+            .map_err(Errors::from)?;
+            if suspended {
+                return Err(Errors::with_field("user", "is suspended")
+                    .code(StatusCode::FORBIDDEN)
+                    .into());
+            }
+            Ok(auth)
+        })
+    }
+}
+
+impl Auth {
+    fn from_headers(req: &HttpRequest) -> Result<Self, Error> {
         let headers = req.headers();
         if !headers.contains_key(AUTHORIZATION) {
-            return err(ErrorUnauthorized(""));
+            return Err(ErrorUnauthorized(""));
         }
 
         let token = headers.get(AUTHORIZATION).unwrap().to_str().unwrap_or("");
@@ -39,15 +71,14 @@ impl FromRequest for Auth {
             let jwt = &token[prefix.len()..];
 
             if let Ok(claims) = Claims::decode(jwt.to_owned(), "secret") {
-                let auth = Auth {
+                return Ok(Auth {
                     jwt: jwt.to_owned(),
                     claims,
-                };
-                return ok(auth);
+                });
             }
         }
 
-        err(ErrorUnauthorized("error unauthorized"))
+        Err(ErrorUnauthorized("error unauthorized"))
     }
 }
 
@@ -0,0 +1,127 @@
+use image::{
+    imageops::FilterType, DynamicImage, ImageDecoder, ImageFormat, ImageReader, ImageResult,
+    Limits, Rgba, RgbaImage,
+};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+
+/// Edge lengths, in pixels, every avatar is rendered at.
+pub const SIZES: [u32; 3] = [64, 128, 256];
+
+/// Images larger than this on either side are refused before decoding.
+const MAX_DIMENSION: u32 = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Png,
+    Webp,
+}
+
+impl Format {
+    pub const ALL: [Format; 2] = [Format::Png, Format::Webp];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "png" => Some(Format::Png),
+            "webp" => Some(Format::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Webp => "webp",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Png => "image/png",
+            Format::Webp => "image/webp",
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Format::Png => ImageFormat::Png,
+            Format::Webp => ImageFormat::WebP,
+        }
+    }
+}
+
+/// One encoded size of an avatar.
+pub struct Rendition {
+    pub size: u32,
+    pub format: Format,
+    pub data: Vec<u8>,
+}
+
+/// Decodes an uploaded image, turns it upright, crops it to a centred square
+/// and encodes it at every size in every format. Re-encoding drops EXIF and
+/// any other metadata the original carried.
+pub fn process(data: &[u8]) -> ImageResult<Vec<Rendition>> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let side = image.width().min(image.height());
+    let square = image.crop_imm(
+        (image.width() - side) / 2,
+        (image.height() - side) / 2,
+        side,
+        side,
+    );
+    let square = DynamicImage::ImageRgba8(square.to_rgba8());
+
+    let mut renditions = Vec::new();
+    for &size in SIZES.iter() {
+        let resized = square.resize_exact(size, size, FilterType::Lanczos3);
+        for &format in Format::ALL.iter() {
+            renditions.push(Rendition {
+                size,
+                format,
+                data: encode(&resized, format)?,
+            });
+        }
+    }
+    Ok(renditions)
+}
+
+fn encode(image: &DynamicImage, format: Format) -> ImageResult<Vec<u8>> {
+    let mut data = Cursor::new(Vec::new());
+    image.write_to(&mut data, format.image_format())?;
+    Ok(data.into_inner())
+}
+
+/// The identicon seed for `username`. Hashing keeps the name out of the URL
+/// while giving every user the same picture on every request.
+pub fn seed(username: &str) -> String {
+    format!("{:x}", Sha256::digest(username.as_bytes()))[..16].to_string()
+}
+
+/// Draws a horizontally symmetric 5x5 identicon for `seed`, GitHub style.
+pub fn identicon(seed: &str, size: u32, format: Format) -> ImageResult<Vec<u8>> {
+    let hash = Sha256::digest(seed.as_bytes());
+    let background = Rgba([240, 240, 240, 255]);
+    let foreground = Rgba([hash[0] / 2 + 64, hash[1] / 2 + 64, hash[2] / 2 + 64, 255]);
+
+    let mut grid = RgbaImage::from_pixel(5, 5, background);
+    for y in 0..5 {
+        for x in 0..3 {
+            if hash[3 + (y * 3 + x) as usize] % 2 == 0 {
+                grid.put_pixel(x, y, foreground);
+                grid.put_pixel(4 - x, y, foreground);
+            }
+        }
+    }
+    let image = DynamicImage::ImageRgba8(grid).resize_exact(size, size, FilterType::Nearest);
+    encode(&image, format)
+}
@@ -0,0 +1,164 @@
+use ab_glyph::{point, Font, FontArc, PxScale, ScaleFont};
+use image::{DynamicImage, ImageFormat, ImageResult, Rgba, RgbaImage};
+use sha2::{Digest, Sha256};
+use std::{fs, io::Cursor};
+
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+
+const PADDING: f32 = 80.0;
+const TITLE_SIZE: f32 = 68.0;
+const TITLE_LINES: usize = 4;
+const AUTHOR_SIZE: f32 = 36.0;
+
+/// Draws the default social card of articles without a cover image.
+pub struct CardRenderer {
+    font: Option<FontArc>,
+}
+
+impl CardRenderer {
+    /// Loads the font cards are set in. Without one, cards are still drawn
+    /// but carry no text.
+    pub fn load(font_path: &str) -> Self {
+        let font = fs::read(font_path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| FontArc::try_from_vec(data).map_err(|e| e.to_string()));
+        match font {
+            Ok(font) => CardRenderer { font: Some(font) },
+            Err(e) => {
+                warn!("cannot load card font {}: {}", font_path, e);
+                CardRenderer { font: None }
+            }
+        }
+    }
+
+    /// A PNG with `title` wrapped over up to four lines and `author` below,
+    /// on a background colour picked from `seed`.
+    pub fn render(&self, seed: &str, title: &str, author: &str) -> ImageResult<Vec<u8>> {
+        let hash = Sha256::digest(seed.as_bytes());
+        let top = Rgba([hash[0] / 4 + 16, hash[1] / 4 + 16, hash[2] / 4 + 32, 255]);
+        let bottom = Rgba([top[0] / 2, top[1] / 2, top[2] / 2, 255]);
+        let mut card = RgbaImage::from_fn(WIDTH, HEIGHT, |_, y| {
+            let t = y as f32 / HEIGHT as f32;
+            let mix = |a: u8, b: u8| (a as f32 * (1.0 - t) + b as f32 * t) as u8;
+            Rgba([
+                mix(top[0], bottom[0]),
+                mix(top[1], bottom[1]),
+                mix(top[2], bottom[2]),
+                255,
+            ])
+        });
+
+        if let Some(font) = &self.font {
+            let max_width = WIDTH as f32 - 2.0 * PADDING;
+            let title_font = font.as_scaled(PxScale::from(TITLE_SIZE));
+            let mut y = PADDING + title_font.ascent();
+            for line in wrap(font, TITLE_SIZE, title, max_width, TITLE_LINES) {
+                draw_text(&mut card, font, TITLE_SIZE, PADDING, y, &line);
+                y += title_font.height() + title_font.line_gap();
+            }
+
+            let author_font = font.as_scaled(PxScale::from(AUTHOR_SIZE));
+            let byline = format!("by {}", author);
+            let y = HEIGHT as f32 - PADDING + author_font.descent();
+            draw_text(&mut card, font, AUTHOR_SIZE, PADDING, y, &byline);
+        }
+
+        let mut data = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(card).write_to(&mut data, ImageFormat::Png)?;
+        Ok(data.into_inner())
+    }
+}
+
+fn text_width(font: &FontArc, size: f32, text: &str) -> f32 {
+    let font = font.as_scaled(PxScale::from(size));
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, id);
+        }
+        width += font.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Breaks `text` into lines no wider than `max_width`, ending the last one
+/// with an ellipsis if it does not fit in `max_lines`.
+fn wrap(font: &FontArc, size: f32, text: &str, max_width: f32, max_lines: usize) -> Vec<String> {
+    let fits = |line: &str| text_width(font, size, line) <= max_width;
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if fits(&candidate) {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        // A word wider than a whole line is split wherever it overflows.
+        line = String::new();
+        for c in word.chars() {
+            line.push(c);
+            if !fits(&line) {
+                line.pop();
+                lines.push(line);
+                line = c.to_string();
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last = lines.last_mut().unwrap();
+        while !last.is_empty() && !fits(&format!("{}…", last)) {
+            last.pop();
+        }
+        last.push('…');
+    }
+    lines
+}
+
+/// Draws `text` in white with its baseline at `y`.
+fn draw_text(card: &mut RgbaImage, font: &FontArc, size: f32, x: f32, y: f32, text: &str) {
+    let scale = PxScale::from(size);
+    let scaled = font.as_scaled(scale);
+    let mut caret = x;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += scaled.kern(previous, id);
+        }
+        let glyph = id.with_scale_and_position(scale, point(caret, y));
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+
+        if let Some(outline) = font.outline_glyph(glyph) {
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px < 0 || py < 0 || px >= WIDTH as i32 || py >= HEIGHT as i32 {
+                    return;
+                }
+                let pixel = card.get_pixel_mut(px as u32, py as u32);
+                for channel in 0..3 {
+                    let value = pixel[channel] as f32 * (1.0 - coverage) + 255.0 * coverage;
+                    pixel[channel] = value as u8;
+                }
+            });
+        }
+    }
+}
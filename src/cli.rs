@@ -0,0 +1,109 @@
+use crate::{
+    archive,
+    db::{self, article, card, reaction, sitemap, upload, user::ROLES, User},
+    db_pool,
+    errors::CliError,
+    storage::{BlobStore, LocalStore},
+    trash, Settings,
+};
+use std::{collections::HashSet, path::Path, time::SystemTime};
+
+const EXPORT_USAGE: &str = "export <directory or .tar[.gz]> [--author <username>]";
+const IMPORT_USAGE: &str = "import <directory or .tar[.gz]> [--dry-run]";
+const SET_ROLE_USAGE: &str = "set-role <username> <user|moderator|admin>";
+
+/// Runs a maintenance command given on the command line instead of
+/// starting the server.
+pub fn run(settings: &Settings, args: &[String]) -> Result<(), CliError> {
+    let pool = db_pool(&settings.database_url)?;
+    let conn = pool.get()?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args[0] {
+        "recount-favorites" => {
+            let repaired = article::recount_favorites(&conn)?;
+            info!("repaired favorites_count of {} articles", repaired);
+            Ok(())
+        }
+        "recount-reactions" => {
+            let repaired = reaction::recount(&conn)?;
+            info!("repaired reaction counts of {} articles and comments", repaired);
+            Ok(())
+        }
+        "gc-uploads" => {
+            let store = LocalStore::new(&settings.upload_dir);
+            let orphans = upload::delete_orphans(&conn)?;
+            info!("deleted {} orphaned uploads", orphans.len());
+            // Blobs can also be left behind by a failed request, so sweep
+            // everything the store holds that no upload refers to, leaving
+            // recent files to the requests that may still be recording them.
+            let mut live: HashSet<String> = upload::hashes(&conn)?.into_iter().collect();
+            live.extend(card::hashes(&conn)?);
+            let grace = std::time::Duration::from_secs(upload::ORPHAN_GRACE_HOURS as u64 * 60 * 60);
+            let before = SystemTime::now() - grace;
+            let mut removed = 0;
+            for key in store.keys(before)? {
+                if !live.contains(&key) {
+                    store.delete(&key)?;
+                    removed += 1;
+                }
+            }
+            info!("removed {} unreferenced files", removed);
+            Ok(())
+        }
+        "purge-trash" => {
+            let retention = chrono::Duration::days(settings.trash_retention_days);
+            let (articles, comments) = trash::purge(&conn, retention)?;
+            info!(
+                "purged {} articles and {} comments from the trash",
+                articles, comments
+            );
+            Ok(())
+        }
+        "rebuild-sitemap" => {
+            let count = sitemap::rebuild(&conn)?;
+            info!("rebuilt sitemap with {} entries", count);
+            Ok(())
+        }
+        "export" => {
+            let (path, author) = match args[1..] {
+                [path] => (path, None),
+                [path, "--author", author] => (path, Some(author)),
+                _ => return Err(CliError::Usage(EXPORT_USAGE)),
+            };
+            let documents = db::archive::export(&conn, author)?;
+            archive::write(Path::new(path), &documents)?;
+            info!("exported {} articles to {}", documents.len(), path);
+            Ok(())
+        }
+        "import" => {
+            let (path, dry_run) = match args[1..] {
+                [path] => (path, false),
+                [path, "--dry-run"] => (path, true),
+                _ => return Err(CliError::Usage(IMPORT_USAGE)),
+            };
+            let entries = archive::read(Path::new(path))?;
+            let report = db::archive::import(&conn, entries, dry_run)?;
+            for failure in &report.failed {
+                warn!("{}: {}", failure.file, failure.error);
+            }
+            info!(
+                "{}{} created, {} updated, {} unchanged, {} failed",
+                if dry_run { "dry run: " } else { "" },
+                report.created.len(),
+                report.updated.len(),
+                report.unchanged.len(),
+                report.failed.len()
+            );
+            Ok(())
+        }
+        "set-role" => match args[1..] {
+            [username, role] if ROLES.contains(&role) => {
+                User::set_role(&conn, username, role)?;
+                info!("{} is now {}", username, role);
+                Ok(())
+            }
+            _ => Err(CliError::Usage(SET_ROLE_USAGE)),
+        },
+        other => Err(CliError::UnknownCommand(other.to_owned())),
+    }
+}
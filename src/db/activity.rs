@@ -0,0 +1,60 @@
+use crate::schema::*;
+use chrono::{DateTime, Utc};
+use diesel::{
+    dsl::{count_star, exists},
+    prelude::*,
+    result::Error,
+};
+
+/// Whether `author` published an article with this body since `since`,
+/// other than the one at `except`.
+pub fn article_duplicate(
+    conn: &PgConnection,
+    author: i32,
+    body: &str,
+    since: DateTime<Utc>,
+    except: Option<&str>,
+) -> Result<bool, Error> {
+    diesel::select(exists(
+        articles::table
+            .filter(articles::author.eq(author))
+            .filter(articles::body.eq(body))
+            .filter(articles::created_at.ge(since))
+            .filter(articles::slug.ne(except.unwrap_or("")))
+            .filter(articles::deleted_at.is_null()),
+    ))
+    .get_result(conn)
+}
+
+/// Whether `author` posted a comment with this body since `since`.
+pub fn comment_duplicate(
+    conn: &PgConnection,
+    author: i32,
+    body: &str,
+    since: DateTime<Utc>,
+) -> Result<bool, Error> {
+    diesel::select(exists(
+        comments::table
+            .filter(comments::author.eq(author))
+            .filter(comments::body.eq(body))
+            .filter(comments::created_at.ge(since))
+            .filter(comments::deleted_at.is_null()),
+    ))
+    .get_result(conn)
+}
+
+/// Articles and comments `author` wrote since `since`, counting deleted
+/// ones so deleting does not make room for more.
+pub fn posts_since(conn: &PgConnection, author: i32, since: DateTime<Utc>) -> Result<i64, Error> {
+    let articles = articles::table
+        .filter(articles::author.eq(author))
+        .filter(articles::created_at.ge(since))
+        .select(count_star())
+        .first::<i64>(conn)?;
+    let comments = comments::table
+        .filter(comments::author.eq(author))
+        .filter(comments::created_at.ge(since))
+        .select(count_star())
+        .first::<i64>(conn)?;
+    Ok(articles + comments)
+}
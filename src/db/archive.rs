@@ -0,0 +1,301 @@
+use super::{
+    article::{self, ArticleForm, ArticleUpdateData},
+    sitemap, User,
+};
+use crate::{
+    archive::{Document, Entry, FrontMatter},
+    errors::Errors,
+    schema::*,
+    validation::{
+        normalize_tags, validate_cover_image, validate_cover_upload, validate_length,
+        validate_tags, MAX_DESCRIPTION_LENGTH, MAX_TITLE_LENGTH,
+    },
+};
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, result::Error};
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct Failure {
+    pub file: String,
+    pub error: String,
+}
+
+/// What an import did, or would have done for a dry run.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub failed: Vec<Failure>,
+    pub dry_run: bool,
+}
+
+type Row = (
+    String,
+    String,
+    String,
+    String,
+    Vec<String>,
+    Option<String>,
+    DateTime<Utc>,
+    DateTime<Utc>,
+    String,
+);
+
+/// Every article, or those of `author`, oldest first.
+pub fn export(conn: &PgConnection, author: Option<&str>) -> Result<Vec<Document>, Error> {
+    let mut query = articles::table
+        .inner_join(users::table)
+        .select((
+            articles::slug,
+            articles::title,
+            articles::description,
+            articles::body,
+            articles::tag_list,
+            articles::cover_image,
+            articles::created_at,
+            articles::updated_at,
+            users::username,
+        ))
+        .filter(articles::deleted_at.is_null())
+        .order(articles::id)
+        .into_boxed();
+    if let Some(author) = author {
+        User::with_username(conn, author)?;
+        query = query.filter(users::username.eq(author));
+    }
+    let rows = query.load::<Row>(conn)?;
+    Ok(rows
+        .into_iter()
+        .map(
+            |(
+                slug,
+                title,
+                description,
+                body,
+                tags,
+                cover_image,
+                created_at,
+                updated_at,
+                author,
+            )| {
+                Document {
+                    front: FrontMatter {
+                        title,
+                        slug: Some(slug),
+                        description,
+                        author,
+                        tags,
+                        cover_image,
+                        created_at: Some(created_at),
+                        updated_at: Some(updated_at),
+                    },
+                    body,
+                }
+            },
+        )
+        .collect())
+}
+
+enum Outcome {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// The slug of `document`, falling back to the name of its file.
+fn slug(file: &str, document: &Document) -> Result<String, String> {
+    let slug = match document.front.slug {
+        Some(ref slug) => slug.clone(),
+        None => file.trim_end_matches(".md").to_string(),
+    };
+    if slug.is_empty() || slug::slugify(&slug) != slug {
+        return Err(format!("invalid slug {:?}", slug));
+    }
+    Ok(slug)
+}
+
+fn import_one(conn: &PgConnection, file: &str, document: Document) -> Result<Outcome, String> {
+    let slug = slug(file, &document)?;
+    let front = document.front;
+    if front.title.trim().is_empty() {
+        return Err("title is empty".to_string());
+    }
+    // The same checks as articles written through the API.
+    let tags = normalize_tags(front.tags);
+    let mut errors = Errors::new();
+    validate_length(&mut errors, "title", &front.title, MAX_TITLE_LENGTH);
+    validate_length(
+        &mut errors,
+        "description",
+        &front.description,
+        MAX_DESCRIPTION_LENGTH,
+    );
+    validate_tags(&mut errors, "tags", &tags);
+    if let Some(ref url) = front.cover_image {
+        validate_cover_image(&mut errors, url);
+    }
+    if !errors.is_empty() {
+        return Err(errors.to_string());
+    }
+    let author = match User::with_username(conn, &front.author) {
+        Ok(user) => user,
+        Err(Error::NotFound) => return Err(format!("unknown author {:?}", front.author)),
+        Err(e) => return Err(e.to_string()),
+    };
+    if let Some(ref url) = front.cover_image {
+        validate_cover_upload(conn, &mut errors, author.id, url).map_err(|e| e.to_string())?;
+        if !errors.is_empty() {
+            return Err(errors.to_string());
+        }
+    }
+    let existing = articles::table
+        .filter(articles::slug.eq(&slug))
+        .select((
+            articles::author,
+            articles::deleted_at,
+            articles::title,
+            articles::description,
+            articles::body,
+            articles::tag_list,
+            articles::cover_image,
+            articles::created_at,
+            articles::updated_at,
+        ))
+        .first::<(
+            i32,
+            Option<DateTime<Utc>>,
+            String,
+            String,
+            String,
+            Vec<String>,
+            Option<String>,
+            DateTime<Utc>,
+            DateTime<Utc>,
+        )>(conn)
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let outcome = match existing {
+        Some((owner, ..)) if owner != author.id => {
+            return Err(format!("slug {:?} belongs to another author", slug))
+        }
+        Some((_, Some(_), ..)) => return Err(format!("slug {:?} is in the trash", slug)),
+        Some((
+            _,
+            None,
+            title,
+            description,
+            body,
+            mut current_tags,
+            cover_image,
+            created_at,
+            updated_at,
+        )) => {
+            current_tags.sort();
+            let mut sorted_tags = tags.clone();
+            sorted_tags.sort();
+            let unchanged = title == front.title
+                && description == front.description
+                && body == document.body
+                && current_tags == sorted_tags
+                && cover_image == front.cover_image
+                && front.created_at.is_none_or(|t| t == created_at)
+                && front.updated_at.is_none_or(|t| t == updated_at);
+            if unchanged {
+                return Ok(Outcome::Unchanged);
+            }
+            let form = ArticleUpdateData {
+                slug: None,
+                title: Some(front.title),
+                description: Some(front.description),
+                body: Some(document.body),
+                tag_list: Some(tags),
+                cover_image: Some(front.cover_image),
+            };
+            article::update(conn, &slug, author.id, &form).map_err(|e| e.to_string())?;
+            Outcome::Updated
+        }
+        None => {
+            let form = ArticleForm {
+                slug: slug.clone(),
+                title: front.title,
+                description: front.description,
+                body: document.body,
+                tag_list: tags,
+                author: author.id,
+                cover_image: front.cover_image,
+            };
+            article::create(conn, &form).map_err(|e| e.to_string())?;
+            Outcome::Created
+        }
+    };
+
+    // Keep the timestamps of the source, so a re-import sees no change.
+    let (id, created_at, updated_at) = articles::table
+        .filter(articles::slug.eq(&slug))
+        .select((articles::id, articles::created_at, articles::updated_at))
+        .first::<(i32, DateTime<Utc>, DateTime<Utc>)>(conn)
+        .map_err(|e| e.to_string())?;
+    let created_at = front.created_at.unwrap_or(created_at);
+    let updated_at = front.updated_at.unwrap_or(updated_at).max(created_at);
+    diesel::update(articles::table.find(id))
+        .set((
+            articles::created_at.eq(created_at),
+            articles::updated_at.eq(updated_at),
+        ))
+        .execute(conn)
+        .map_err(|e| e.to_string())?;
+    sitemap::article(conn, id).map_err(|e| e.to_string())?;
+    Ok(outcome)
+}
+
+/// Creates or updates an article for each document, matching them by slug
+/// and their authors by username. Documents already in the database as
+/// they are count as unchanged, so importing the same files twice is
+/// harmless. A dry run reports the same but rolls everything back.
+///
+/// Imports skip the content filters: only admins run them, and restoring an
+/// export would otherwise trip the duplicate check on every article.
+pub fn import(conn: &PgConnection, entries: Vec<Entry>, dry_run: bool) -> Result<Report, Error> {
+    let mut report = Report {
+        dry_run,
+        ..Report::default()
+    };
+    let result = conn.transaction::<_, Error, _>(|| {
+        for (file, document) in entries {
+            // Each file gets a savepoint, so one bad file does not undo the
+            // others.
+            let outcome = document.and_then(|document| {
+                let mut outcome = Err(String::new());
+                let result = conn.transaction::<_, Error, _>(|| {
+                    outcome = import_one(conn, &file, document);
+                    if outcome.is_err() {
+                        return Err(Error::RollbackTransaction);
+                    }
+                    Ok(())
+                });
+                match result {
+                    Ok(()) | Err(Error::RollbackTransaction) => outcome,
+                    Err(e) => Err(e.to_string()),
+                }
+            });
+            match outcome {
+                Ok(Outcome::Created) => report.created.push(file),
+                Ok(Outcome::Updated) => report.updated.push(file),
+                Ok(Outcome::Unchanged) => report.unchanged.push(file),
+                Err(error) => report.failed.push(Failure { file, error }),
+            }
+        }
+        if dry_run {
+            return Err(Error::RollbackTransaction);
+        }
+        Ok(())
+    });
+    match result {
+        Ok(()) | Err(Error::RollbackTransaction) => Ok(report),
+        Err(e) => Err(e),
+    }
+}
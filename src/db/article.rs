@@ -1,8 +1,16 @@
 use super::*;
-use crate::schema::*;
+use crate::{
+    db::series::SeriesInfo,
+    markdown::{self, Heading},
+    schema::{series, *},
+};
 use chrono::{DateTime, Utc};
-use diesel::{prelude::*, result::Error};
+use diesel::{
+    prelude::*,
+    result::{DatabaseErrorKind, Error},
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -14,38 +22,110 @@ pub struct Articles {
 #[derive(Serialize, Deserialize,Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Article {
+    /// Not part of the API, but feeds identify entries by it.
+    #[serde(skip)]
+    pub id: i32,
     pub slug: String,
     pub title: String,
     pub description: String,
     pub body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_html: Option<String>,
+    pub cover_image: Option<String>,
     pub tag_list: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub favorited: bool,
     pub favorites_count: i32,
+    pub bookmarked: bool,
+    pub reactions: reaction::Counts,
+    pub my_reactions: Vec<String>,
+    pub word_count: i32,
+    pub reading_time: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toc: Option<Vec<Heading>>,
     pub author: Profile,
+    /// The author followed by accepted co-authors in order.
+    pub authors: Vec<Profile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<SeriesInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_reason: Option<FeedReason>,
+    /// Only ever shown to the author, as nobody else sees hidden articles.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hidden_at: Option<DateTime<Utc>>,
+    /// Only set on articles in the author's trash.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Why an article showed up in the personal feed.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedReason {
+    pub followed_author: bool,
+    pub tags: Vec<String>,
 }
 
 impl Article {
     fn build(article: ArticleData, profile: Profile) -> Self {
         Article {
+            id: article.id,
             slug: article.slug,
             title: article.title,
             description: article.description,
             body: article.body,
+            body_html: article.body_html,
+            cover_image: article.cover_image,
             tag_list: article.tag_list,
             created_at: article.created_at,
             updated_at: article.updated_at,
             favorited: false,
             favorites_count: article.favorites_count,
+            bookmarked: false,
+            reactions: reaction::counts(article.reaction_counts),
+            my_reactions: Vec::new(),
+            word_count: article.word_count,
+            reading_time: article.reading_time,
+            toc: article.toc.and_then(|toc| serde_json::from_value(toc).ok()),
+            authors: vec![profile.clone()],
             author: profile,
+            series: None,
+            feed_reason: None,
+            hidden_at: article.hidden_at,
+            deleted_at: article.deleted_at,
         }
     }
 
+    fn coauthors(mut self, coauthors: Vec<Profile>) -> Self {
+        self.authors.extend(coauthors);
+        self
+    }
+
+    fn series(mut self, series: Option<SeriesInfo>) -> Self {
+        self.series = series;
+        self
+    }
+
     fn favorite(mut self, f: bool) -> Self {
         self.favorited = f;
         self
     }
+
+    fn bookmark(mut self, b: bool) -> Self {
+        self.bookmarked = b;
+        self
+    }
+
+    fn my_reactions(mut self, reactions: Vec<String>) -> Self {
+        self.my_reactions = reactions;
+        self
+    }
+
+    fn reason(mut self, reason: FeedReason) -> Self {
+        self.feed_reason = Some(reason);
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Queryable)]
@@ -61,6 +141,15 @@ struct ArticleData {
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
     favorites_count: i32,
+    body_html: Option<String>,
+    word_count: i32,
+    reading_time: i32,
+    toc: Option<serde_json::Value>,
+    reaction_counts: serde_json::Value,
+    cover_image: Option<String>,
+    hidden_at: Option<DateTime<Utc>>,
+    deleted_at: Option<DateTime<Utc>>,
+    removed_by: Option<i32>,
 }
 
 #[derive(Insertable, AsChangeset, Default, Clone)]
@@ -72,29 +161,123 @@ pub struct ArticleForm {
     pub body: String,
     pub tag_list: Vec<String>,
     pub author: i32,
+    pub cover_image: Option<String>,
 }
 
-#[derive(Deserialize, Default, Clone)]
-pub struct ArticleUpdate {
-    pub article: ArticleUpdateData,
-}
-
-#[derive(Deserialize, AsChangeset, Default, Clone)]
+#[derive(AsChangeset, Default, Clone)]
 #[table_name = "articles"]
 pub struct ArticleUpdateData {
     pub slug: Option<String>,
     pub title: Option<String>,
     pub description: Option<String>,
     pub body: Option<String>,
+    pub tag_list: Option<Vec<String>>,
+    /// `Some(None)` removes the cover image.
+    pub cover_image: Option<Option<String>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ArticleQuery {
     pub tag: Option<String>,
     pub author: Option<String>,
     pub favorited: Option<String>,
+    pub series: Option<String>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    #[serde(rename = "minReadingTime")]
+    pub min_reading_time: Option<i32>,
+    #[serde(rename = "maxReadingTime")]
+    pub max_reading_time: Option<i32>,
+}
+
+/// Co-authors, series placement and viewer state for a page of articles, loaded up front so
+/// building the page costs a fixed number of queries.
+struct Batch {
+    viewer: Viewer,
+    coauthors: HashMap<i32, Vec<User>>,
+    series: HashMap<i32, SeriesInfo>,
+}
+
+impl Batch {
+    fn load(
+        conn: &PgConnection,
+        viewer: Option<i32>,
+        rows: &[(ArticleData, User)],
+    ) -> Result<Self, Error> {
+        let ids: Vec<i32> = rows.iter().map(|(a, _)| a.id).collect();
+        let mut coauthors: HashMap<i32, Vec<User>> = HashMap::new();
+        if !ids.is_empty() {
+            let accepted = article_authors::table
+                .inner_join(users::table)
+                .filter(article_authors::article.eq_any(&ids))
+                .filter(article_authors::accepted_at.is_not_null())
+                .select((article_authors::article, users::all_columns))
+                .order(article_authors::position)
+                .load::<(i32, User)>(conn)?;
+            for (article, user) in accepted {
+                coauthors.entry(article).or_default().push(user);
+            }
+        }
+
+        let mut authors: Vec<i32> = rows.iter().map(|(a, _)| a.author).collect();
+        authors.extend(coauthors.values().flatten().map(|u| u.id));
+        let viewer = Viewer::load(conn, viewer, &authors, &ids)?;
+        let series = super::series::info(conn, &ids)?;
+        Ok(Batch {
+            viewer,
+            coauthors,
+            series,
+        })
+    }
+
+    /// Whether the viewer follows any author of `article`.
+    fn follows_author(&self, article: &ArticleData) -> bool {
+        self.viewer.follows(article.author)
+            || self
+                .coauthors
+                .get(&article.id)
+                .is_some_and(|users| users.iter().any(|u| self.viewer.follows(u.id)))
+    }
+}
+
+fn build_all(rows: Vec<(ArticleData, User)>, mut batch: Batch) -> Vec<Article> {
+    rows.into_iter()
+        .map(|(article, author)| {
+            let viewer = &batch.viewer;
+            let profile = author.to_profile(viewer.follows(author.id));
+            let favorited = viewer.favorited(article.id);
+            let bookmarked = viewer.bookmarked(article.id);
+            let my_reactions = viewer.article_reactions(article.id);
+            let coauthors = batch
+                .coauthors
+                .remove(&article.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|u| {
+                    let following = viewer.follows(u.id);
+                    u.to_profile(following)
+                })
+                .collect();
+            let series = batch.series.remove(&article.id);
+            Article::build(article, profile)
+                .coauthors(coauthors)
+                .series(series)
+                .favorite(favorited)
+                .bookmark(bookmarked)
+                .my_reactions(my_reactions)
+        })
+        .collect()
+}
+
+fn build_one(
+    conn: &PgConnection,
+    article: ArticleData,
+    author: User,
+    viewer: Option<i32>,
+) -> Result<Article, Error> {
+    let rows = vec![(article, author)];
+    let batch = Batch::load(conn, viewer, &rows)?;
+    Ok(build_all(rows, batch).remove(0))
 }
 
 pub fn list_articles(
@@ -104,43 +287,80 @@ pub fn list_articles(
 ) -> Result<Articles, Error> {
     let mut query = articles::table
         .inner_join(users::table)
-        .left_join(
-            favorites::table.on(articles::id
-                .eq(favorites::article)
-                .and(favorites::user.eq(user_id.unwrap_or(0)))),
-        )
-        .select((
-            articles::all_columns,
-            users::all_columns,
-            favorites::user.nullable().is_not_null(),
-        ))
+        .filter(articles::deleted_at.is_null())
+        .select((articles::all_columns, users::all_columns))
         .into_boxed();
 
+    query = match user_id {
+        Some(user_id) => query.filter(
+            articles::hidden_at
+                .is_null()
+                .or(articles::author.eq(user_id))
+                .or(articles::id.eq_any(
+                    article_authors::table
+                        .select(article_authors::article)
+                        .filter(article_authors::user.eq(user_id))
+                        .filter(article_authors::accepted_at.is_not_null()),
+                )),
+        ),
+        None => query.filter(articles::hidden_at.is_null()),
+    };
     if let Some(ref tag) = form.tag {
-        query = query.filter(articles::tag_list.contains(vec![tag]))
+        query = query.filter(articles::tag_list.contains(vec![tag::normalize(tag)]))
     }
     if let Some(ref author) = form.author {
-        query = query.or_filter(users::username.eq(author))
+        let uid = users::table
+            .select(users::id)
+            .filter(users::username.eq(author))
+            .first::<i32>(conn)
+            .optional()?;
+        let uid = uid.unwrap_or_default();
+        query = query.filter(
+            articles::author.eq(uid).or(articles::id.eq_any(
+                article_authors::table
+                    .select(article_authors::article)
+                    .filter(article_authors::user.eq(uid))
+                    .filter(article_authors::accepted_at.is_not_null()),
+            )),
+        )
     }
     if let Some(ref favorited) = form.favorited {
         let uid = users::table
             .select(users::id)
             .filter(users::username.eq(favorited))
-            .get_result::<i32>(conn)?;
-        query = query.or_filter(favorites::user.eq(uid))
+            .first::<i32>(conn)
+            .optional()?;
+        query = query.filter(
+            articles::id.eq_any(
+                favorites::table
+                    .select(favorites::article)
+                    .filter(favorites::user.eq(uid.unwrap_or_default())),
+            ),
+        )
+    }
+    if let Some(ref slug) = form.series {
+        query = query.filter(
+            articles::id.eq_any(
+                series_articles::table
+                    .inner_join(series::table)
+                    .select(series_articles::article)
+                    .filter(series::slug.eq(slug)),
+            ),
+        )
+    }
+    if let Some(min) = form.min_reading_time {
+        query = query.filter(articles::reading_time.ge(min))
     }
-    let articles: Vec<Article> = query
+    if let Some(max) = form.max_reading_time {
+        query = query.filter(articles::reading_time.le(max))
+    }
+    let rows = query
+        .order(articles::created_at.desc())
         .offset(form.offset.unwrap_or(0))
         .limit(form.limit.unwrap_or(20))
-        .load::<(ArticleData, User, bool)>(conn)
-        .map(|res| {
-            res.into_iter()
-                .map(|(article, author, favorited)| {
-                    Article::build(article, author.to_profile(false)).favorite(favorited)
-                })
-                .collect()
-        })
-        .unwrap_or(vec![]);
+        .load::<(ArticleData, User)>(conn)?;
+    let batch = Batch::load(conn, user_id, &rows)?;
+    let articles = build_all(rows, batch);
     let articles_count = articles.len() as i64;
     Ok(Articles {
         articles,
@@ -149,36 +369,162 @@ pub fn list_articles(
 }
 
 pub fn feed(conn: &PgConnection, form: &ArticleQuery, user_id: i32) -> Result<Articles, Error> {
-    let articles: Vec<Article> = articles::table
+    let followed_authors = follows::table
+        .select(follows::followed)
+        .filter(follows::follower.eq(user_id));
+    let coauthored = article_authors::table
+        .select(article_authors::article)
+        .filter(article_authors::accepted_at.is_not_null())
         .filter(
-            articles::author.eq_any(
+            article_authors::user.eq_any(
                 follows::table
                     .select(follows::followed)
                     .filter(follows::follower.eq(user_id)),
             ),
+        );
+    let followed_tag_articles = article_tags::table
+        .inner_join(tag_follows::table.on(tag_follows::tag.eq(article_tags::tag)))
+        .select(article_tags::article)
+        .filter(tag_follows::follower.eq(user_id));
+
+    let mut query = articles::table
+        .filter(
+            articles::author
+                .eq_any(followed_authors)
+                .or(articles::id.eq_any(coauthored))
+                .or(articles::id.eq_any(followed_tag_articles)),
         )
+        .filter(articles::hidden_at.is_null())
+        .filter(articles::deleted_at.is_null())
         .inner_join(users::table)
-        .left_join(
-            favorites::table.on(articles::id
-                .eq(favorites::article)
-                .and(favorites::user.eq(user_id))),
-        )
-        .select((
-            articles::all_columns,
-            users::all_columns,
-            favorites::user.nullable().is_not_null(),
-        ))
+        .select((articles::all_columns, users::all_columns))
+        .into_boxed();
+
+    if let Some(min) = form.min_reading_time {
+        query = query.filter(articles::reading_time.ge(min))
+    }
+    if let Some(max) = form.max_reading_time {
+        query = query.filter(articles::reading_time.le(max))
+    }
+    let rows = query
+        .order(articles::created_at.desc())
         .limit(form.limit.unwrap_or(20))
         .offset(form.offset.unwrap_or(0))
-        .load::<(ArticleData, User, bool)>(conn)
-        .map(|res| {
-            res.into_iter()
-                .map(|(article, author, favorited)| {
-                    Article::build(article, author.to_profile(false)).favorite(favorited)
-                })
-                .collect()
+        .load::<(ArticleData, User)>(conn)?;
+
+    let batch = Batch::load(conn, Some(user_id), &rows)?;
+    let followed_tags = tag::followed(conn, user_id)?;
+    let reasons: Vec<FeedReason> = rows
+        .iter()
+        .map(|(article, _)| FeedReason {
+            followed_author: batch.follows_author(article),
+            tags: article
+                .tag_list
+                .iter()
+                .filter(|t| followed_tags.contains(t))
+                .cloned()
+                .collect(),
         })
-        .unwrap_or(vec![]);
+        .collect();
+
+    let articles: Vec<Article> = build_all(rows, batch)
+        .into_iter()
+        .zip(reasons)
+        .map(|(article, reason)| article.reason(reason))
+        .collect();
+    let articles_count = articles.len() as i64;
+    Ok(Articles {
+        articles,
+        articles_count,
+    })
+}
+
+/// Articles favorited by `username`, most recently favorited first.
+pub fn favorited_by(
+    conn: &PgConnection,
+    username: &str,
+    page: &Page,
+    viewer: Option<i32>,
+) -> Result<Articles, Error> {
+    let user = User::with_username(conn, username)?;
+    let rows = favorites::table
+        .inner_join(articles::table.inner_join(users::table))
+        .filter(favorites::user.eq(user.id))
+        .filter(articles::hidden_at.is_null())
+        .filter(articles::deleted_at.is_null())
+        .select((articles::all_columns, users::all_columns))
+        .order(favorites::created_at.desc())
+        .limit(page.limit())
+        .offset(page.offset())
+        .load::<(ArticleData, User)>(conn)?;
+
+    let batch = Batch::load(conn, viewer, &rows)?;
+    let articles = build_all(rows, batch);
+    let articles_count = articles.len() as i64;
+    Ok(Articles {
+        articles,
+        articles_count,
+    })
+}
+
+/// Articles `user_id` has been invited to co-author but has not accepted yet.
+pub fn invitations(conn: &PgConnection, user_id: i32, page: &Page) -> Result<Articles, Error> {
+    let rows = article_authors::table
+        .inner_join(articles::table.inner_join(users::table))
+        .filter(article_authors::user.eq(user_id))
+        .filter(article_authors::accepted_at.is_null())
+        .filter(articles::deleted_at.is_null())
+        .select((articles::all_columns, users::all_columns))
+        .order(article_authors::invited_at.desc())
+        .limit(page.limit())
+        .offset(page.offset())
+        .load::<(ArticleData, User)>(conn)?;
+
+    let batch = Batch::load(conn, Some(user_id), &rows)?;
+    let articles = build_all(rows, batch);
+    let articles_count = articles.len() as i64;
+    Ok(Articles {
+        articles,
+        articles_count,
+    })
+}
+
+/// Articles related to the one at `slug`, best match first, leaving out
+/// those the viewer has already favorited. There is no way to mute authors or
+/// tags yet, so nothing else is filtered out for the viewer.
+pub fn related(
+    conn: &PgConnection,
+    slug: &str,
+    viewer: Option<i32>,
+    limit: i64,
+) -> Result<Articles, Error> {
+    let article_id = visible_article_id(conn, slug, viewer)?;
+    related::refresh(conn, article_id)?;
+
+    let mut query = related_articles::table
+        .inner_join(articles::table.on(articles::id.eq(related_articles::related)))
+        .inner_join(users::table.on(users::id.eq(articles::author)))
+        .filter(related_articles::article.eq(article_id))
+        .filter(articles::hidden_at.is_null())
+        .filter(articles::deleted_at.is_null())
+        .select((articles::all_columns, users::all_columns))
+        .into_boxed();
+    if let Some(viewer) = viewer {
+        query = query.filter(diesel::dsl::not(
+            articles::id.eq_any(
+                favorites::table
+                    .select(favorites::article)
+                    .filter(favorites::user.eq(viewer)),
+            ),
+        ));
+    }
+    let rows = query
+        .order((related_articles::score.desc(), articles::created_at.desc()))
+        .limit(limit)
+        .load::<(ArticleData, User)>(conn)?;
+
+    let batch = Batch::load(conn, viewer, &rows)?;
+    let articles = build_all(rows, batch);
     let articles_count = articles.len() as i64;
     Ok(Articles {
         articles,
@@ -187,21 +533,181 @@ pub fn feed(conn: &PgConnection, form: &ArticleQuery, user_id: i32) -> Result<Ar
 }
 
 pub fn create(pg: &PgConnection, article: &ArticleForm) -> Result<Article, Error> {
-    let db_article = diesel::insert_into(articles::table)
-        .values(article)
-        .get_result::<ArticleData>(pg)?;
-    let user = User::read(pg, article.author)?;
-    let profile = user.to_profile(false);
-    Ok(Article::build(db_article, profile))
+    pg.transaction::<_, Error, _>(|| {
+        let outline = markdown::outline(&article.body);
+        let db_article = diesel::insert_into(articles::table)
+            .values((
+                article,
+                articles::word_count.eq(outline.word_count),
+                articles::reading_time.eq(outline.reading_time),
+                articles::toc.eq(serde_json::to_value(&outline.toc).ok()),
+            ))
+            .get_result::<ArticleData>(pg)?;
+        revision::record(
+            pg,
+            &RevisionForm {
+                article: db_article.id,
+                author: article.author,
+                title: &db_article.title,
+                description: &db_article.description,
+                body: &db_article.body,
+                changed_fields: vec!["title", "description", "body"],
+                restored_from: None,
+            },
+        )?;
+        tag::sync(pg, db_article.id, &db_article.tag_list)?;
+        related::invalidate(pg, db_article.id, &db_article.tag_list)?;
+        sitemap::article(pg, db_article.id)?;
+        let db_article = with_body_html(pg, db_article)?;
+        let user = User::read(pg, article.author)?;
+        let profile = user.to_profile(false);
+        Ok(Article::build(db_article, profile))
+    })
 }
 
-pub fn get_article(pg: &PgConnection, slug: &str) -> Result<Article, Error> {
-    articles::table
+/// Fills in the rendered body, rendering and caching it if the cache was
+/// invalidated.
+fn with_body_html(conn: &PgConnection, mut article: ArticleData) -> Result<ArticleData, Error> {
+    if article.body_html.is_none() {
+        let html = markdown::render(&article.body);
+        diesel::update(articles::table.find(article.id))
+            .set(articles::body_html.eq(&html))
+            .execute(conn)?;
+        article.body_html = Some(html);
+    }
+    Ok(article)
+}
+
+/// Builds a slug from `title` that no other article uses or used before,
+/// appending `-2`, `-3`, ... on collision. `current` is the slug of the
+/// article being renamed, so it may keep its own slugs.
+pub fn unique_slug(conn: &PgConnection, title: &str, current: Option<&str>) -> Result<String, Error> {
+    let mut base = slug::slugify(title);
+    if base.is_empty() {
+        base = "article".to_string();
+    }
+    let pattern = format!("{}-%", base);
+    let current = current.unwrap_or("");
+
+    let mut taken = articles::table
+        .select(articles::slug)
+        .filter(articles::slug.eq(&base).or(articles::slug.like(&pattern)))
+        .filter(articles::slug.ne(current))
+        .load::<String>(conn)?;
+    taken.extend(
+        slug_history::table
+            .inner_join(articles::table)
+            .select(slug_history::slug)
+            .filter(
+                slug_history::slug
+                    .eq(&base)
+                    .or(slug_history::slug.like(&pattern)),
+            )
+            .filter(articles::slug.ne(current))
+            .load::<String>(conn)?,
+    );
+
+    let mut candidate = base.clone();
+    let mut n = 1;
+    while taken.contains(&candidate) {
+        n += 1;
+        candidate = format!("{}-{}", base, n);
+    }
+    Ok(candidate)
+}
+
+/// How often a write picks another slug after losing it to a concurrent one.
+pub const SLUG_ATTEMPTS: usize = 5;
+
+/// Runs `write` with the slug [`unique_slug`] picks for `title`. Writes of
+/// the same title take turns picking, and when a write still loses its slug
+/// to a concurrent one, such as an import, it is rolled back and run again
+/// with the next free slug.
+pub fn with_unique_slug<T, F>(
+    conn: &PgConnection,
+    title: &str,
+    current: Option<&str>,
+    mut write: F,
+) -> Result<T, Error>
+where
+    F: FnMut(String) -> Result<T, Error>,
+{
+    let mut attempts = 1;
+    loop {
+        let written = conn.transaction(|| {
+            diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext($1))")
+                .bind::<diesel::sql_types::Text, _>(slug::slugify(title))
+                .execute(conn)?;
+            write(unique_slug(conn, title, current)?)
+        });
+        match written {
+            Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, ref info))
+                if info.constraint_name() == Some("articles_slug_key")
+                    && attempts < SLUG_ATTEMPTS =>
+            {
+                attempts += 1
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Looks up the current slug of an article that used to be reachable at `slug`.
+pub fn current_slug(conn: &PgConnection, slug: &str) -> Result<String, Error> {
+    slug_history::table
+        .inner_join(articles::table)
+        .filter(slug_history::slug.eq(slug))
+        .filter(articles::deleted_at.is_null())
+        .select(articles::slug)
+        .first::<String>(conn)
+}
+
+/// Whether `viewer` wrote or co-authors the article `article_id` by `author`.
+fn is_author(
+    conn: &PgConnection,
+    article_id: i32,
+    author: i32,
+    viewer: Option<i32>,
+) -> Result<bool, Error> {
+    let viewer = match viewer {
+        Some(viewer) if viewer == author => return Ok(true),
+        Some(viewer) => viewer,
+        None => return Ok(false),
+    };
+    diesel::select(diesel::dsl::exists(
+        article_authors::table
+            .filter(article_authors::article.eq(article_id))
+            .filter(article_authors::user.eq(viewer))
+            .filter(article_authors::accepted_at.is_not_null()),
+    ))
+    .get_result(conn)
+}
+
+pub fn get_article(pg: &PgConnection, slug: &str, viewer: Option<i32>) -> Result<Article, Error> {
+    let (article, author) = articles::table
         .inner_join(users::table.on(articles::author.eq(users::id)))
         .filter(articles::slug.eq(slug))
+        .filter(articles::deleted_at.is_null())
         .select((articles::all_columns, users::all_columns))
-        .first::<(ArticleData, User)>(pg)
-        .map(|(a, u)| Article::build(a, u.to_profile(false)))
+        .first::<(ArticleData, User)>(pg)?;
+    if article.hidden_at.is_some() && !is_author(pg, article.id, article.author, viewer)? {
+        return Err(Error::NotFound);
+    }
+    build_one(pg, with_body_html(pg, article)?, author, viewer)
+}
+
+/// The id of the article at `slug`, as long as `viewer` can see it: it is
+/// not in the trash, and if it is hidden `viewer` wrote or co-authors it.
+pub fn visible_article_id(conn: &PgConnection, slug: &str, viewer: Option<i32>) -> Result<i32, Error> {
+    let (article_id, author, hidden_at) = articles::table
+        .filter(articles::slug.eq(slug))
+        .filter(articles::deleted_at.is_null())
+        .select((articles::id, articles::author, articles::hidden_at))
+        .first::<(i32, i32, Option<DateTime<Utc>>)>(conn)?;
+    if hidden_at.is_some() && !is_author(conn, article_id, author, viewer)? {
+        return Err(Error::NotFound);
+    }
+    Ok(article_id)
 }
 
 pub fn update(
@@ -210,56 +716,310 @@ pub fn update(
     user_id: i32,
     article: &ArticleUpdateData,
 ) -> Result<Article, Error> {
-    let article = diesel::update(
-        articles::table.filter(articles::slug.eq(slug).and(articles::author.eq(user_id))),
-    )
-    .set(article)
-    .get_result::<ArticleData>(conn)?;
+    write_update(conn, slug, user_id, article, None)
+}
 
-    let author = User::read(conn, article.author)?;
+/// Updates the article like [`update`] with the content of the revision
+/// `revision_id`, recording a revision even if nothing changes.
+pub fn restore_revision(
+    conn: &PgConnection,
+    slug: &str,
+    user_id: i32,
+    article: &ArticleUpdateData,
+    revision_id: i32,
+) -> Result<Article, Error> {
+    write_update(conn, slug, user_id, article, Some(revision_id))
+}
+
+fn write_update(
+    conn: &PgConnection,
+    slug: &str,
+    user_id: i32,
+    article: &ArticleUpdateData,
+    restored_from: Option<i32>,
+) -> Result<Article, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let current = articles::table
+            .filter(articles::slug.eq(slug))
+            .filter(articles::deleted_at.is_null())
+            .filter(
+                articles::author.eq(user_id).or(articles::id.eq_any(
+                    article_authors::table
+                        .select(article_authors::article)
+                        .filter(article_authors::user.eq(user_id))
+                        .filter(article_authors::accepted_at.is_not_null()),
+                )),
+            )
+            .for_update()
+            .first::<ArticleData>(conn)?;
+
+        let mut article = diesel::update(articles::table.find(current.id))
+            .set((article, articles::updated_at.eq(Utc::now())))
+            .get_result::<ArticleData>(conn)?;
+
+        if article.body != current.body {
+            let outline = markdown::outline(&article.body);
+            article = diesel::update(articles::table.find(article.id))
+                .set((
+                    articles::body_html.eq(None::<String>),
+                    articles::word_count.eq(outline.word_count),
+                    articles::reading_time.eq(outline.reading_time),
+                    articles::toc.eq(serde_json::to_value(&outline.toc).ok()),
+                ))
+                .get_result::<ArticleData>(conn)?;
+        }
+
+        if article.tag_list != current.tag_list {
+            tag::sync(conn, article.id, &article.tag_list)?;
+            related::invalidate(conn, article.id, &article.tag_list)?;
+        }
+        sitemap::article(conn, article.id)?;
+        if article.tag_list != current.tag_list {
+            sitemap::prune(conn, article.author, &current.tag_list)?;
+        }
+
+        if article.slug != current.slug {
+            diesel::delete(slug_history::table.find(&article.slug)).execute(conn)?;
+            diesel::insert_into(slug_history::table)
+                .values((
+                    slug_history::slug.eq(&current.slug),
+                    slug_history::article.eq(article.id),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        }
+
+        let mut changed_fields = vec![];
+        if article.title != current.title {
+            changed_fields.push("title");
+        }
+        if article.description != current.description {
+            changed_fields.push("description");
+        }
+        if article.body != current.body {
+            changed_fields.push("body");
+        }
+        if !changed_fields.is_empty() || restored_from.is_some() {
+            revision::record(
+                conn,
+                &RevisionForm {
+                    article: article.id,
+                    author: user_id,
+                    title: &article.title,
+                    description: &article.description,
+                    body: &article.body,
+                    changed_fields,
+                    restored_from,
+                },
+            )?;
+        }
+
+        let article = with_body_html(conn, article)?;
+        let author = User::read(conn, article.author)?;
 
-    Ok(Article::build(article, author.to_profile(false)))
+        build_one(conn, article, author, Some(user_id))
+    })
 }
 
+/// Moves an article to the author's trash. Its comments, favorites and
+/// bookmarks are kept for a restore, but it leaves its tags, related
+/// articles and the sitemap until then.
 pub fn delete(conn: &PgConnection, user_id: i32, slug: &str) -> Result<usize, Error> {
-    diesel::delete(
-        articles::table.filter(articles::slug.eq(slug).and(articles::author.eq(user_id))),
-    )
-    .execute(conn)
+    conn.transaction::<_, Error, _>(|| {
+        let deleted = diesel::update(
+            articles::table
+                .filter(articles::slug.eq(slug).and(articles::author.eq(user_id)))
+                .filter(articles::deleted_at.is_null()),
+        )
+        .set(articles::deleted_at.eq(Utc::now()))
+        .returning((articles::id, articles::author, articles::tag_list))
+        .get_results::<(i32, i32, Vec<String>)>(conn)?;
+        left_trash(conn, &deleted)
+    })
+}
+
+/// Takes articles just moved to the trash out of their tags, related
+/// articles and the sitemap.
+fn left_trash(conn: &PgConnection, deleted: &[(i32, i32, Vec<String>)]) -> Result<usize, Error> {
+    for (article_id, author, tags) in deleted {
+        tag::sync(conn, *article_id, &[])?;
+        related::invalidate(conn, *article_id, tags)?;
+        sitemap::remove_article(conn, *article_id, *author, tags)?;
+    }
+    Ok(deleted.len())
+}
+
+/// Takes an article of `user_id` back out of the trash.
+pub fn restore(conn: &PgConnection, user_id: i32, slug: &str) -> Result<Article, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let article = diesel::update(
+            articles::table
+                .filter(articles::slug.eq(slug).and(articles::author.eq(user_id)))
+                .filter(articles::deleted_at.is_not_null()),
+        )
+        .set((
+            articles::deleted_at.eq(None::<DateTime<Utc>>),
+            articles::removed_by.eq(None::<i32>),
+        ))
+        .get_result::<ArticleData>(conn)?;
+        tag::sync(conn, article.id, &article.tag_list)?;
+        related::invalidate(conn, article.id, &article.tag_list)?;
+        sitemap::article(conn, article.id)?;
+        get_article(conn, slug, Some(user_id))
+    })
+}
+
+/// The articles in the trash of `user_id`, most recently deleted first.
+pub fn trash(conn: &PgConnection, user_id: i32) -> Result<Vec<Article>, Error> {
+    let rows = articles::table
+        .inner_join(users::table)
+        .filter(articles::author.eq(user_id))
+        .filter(articles::deleted_at.is_not_null())
+        .select((articles::all_columns, users::all_columns))
+        .order(articles::deleted_at.desc())
+        .load::<(ArticleData, User)>(conn)?;
+    let batch = Batch::load(conn, Some(user_id), &rows)?;
+    Ok(build_all(rows, batch))
+}
+
+/// Deletes articles that have been in the trash since before `before`,
+/// along with their comments, favorites and bookmarks.
+pub fn purge(conn: &PgConnection, before: DateTime<Utc>) -> Result<usize, Error> {
+    diesel::delete(articles::table.filter(articles::deleted_at.lt(before))).execute(conn)
+}
+
+/// Moves an article to the trash of its author on behalf of `moderator`,
+/// who is recorded as having removed it.
+pub fn remove(conn: &PgConnection, article_id: i32, moderator: i32) -> Result<usize, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let deleted = diesel::update(
+            articles::table
+                .find(article_id)
+                .filter(articles::deleted_at.is_null()),
+        )
+        .set((
+            articles::deleted_at.eq(Utc::now()),
+            articles::removed_by.eq(moderator),
+        ))
+        .returning((articles::id, articles::author, articles::tag_list))
+        .get_results::<(i32, i32, Vec<String>)>(conn)?;
+        left_trash(conn, &deleted)
+    })
 }
 
 pub fn favorite(conn: &PgConnection, user_id: i32, slug: &str) -> Result<Article, Error> {
     conn.transaction::<_, Error, _>(|| {
-        let article_data = diesel::update(articles::table.filter(articles::slug.eq(slug)))
-            .set(articles::favorites_count.eq(articles::favorites_count + 1))
-            .get_result::<ArticleData>(conn)?;
-        diesel::insert_into(favorites::table)
+        let article_id = visible_article_id(conn, slug, Some(user_id))?;
+        let inserted = diesel::insert_into(favorites::table)
             .values((
                 favorites::user.eq(user_id),
-                favorites::article.eq(article_data.id),
+                favorites::article.eq(article_id),
             ))
+            .on_conflict_do_nothing()
             .execute(conn)?;
-        let p = Profile::find_follered(conn, user_id, article_data.author)?;
-        
-        Ok(Article::build(article_data, p).favorite(true))
+        let article_data = diesel::update(articles::table.find(article_id))
+            .set(articles::favorites_count.eq(articles::favorites_count + inserted as i32))
+            .get_result::<ArticleData>(conn)?;
+        let author = User::read(conn, article_data.author)?;
+        build_one(conn, article_data, author, Some(user_id))
     })
 }
 
 pub fn unfavorite(conn: &PgConnection, user_id: i32, slug: &str) -> Result<Article, Error> {
     conn.transaction::<_, Error, _>(|| {
-        let article_data = diesel::update(articles::table.filter(articles::slug.eq(slug)))
-            .set(articles::favorites_count.eq(articles::favorites_count - 1))
+        let article_id = visible_article_id(conn, slug, Some(user_id))?;
+        let deleted = diesel::delete(favorites::table.find((user_id, article_id))).execute(conn)?;
+        let article_data = diesel::update(articles::table.find(article_id))
+            .set(articles::favorites_count.eq(articles::favorites_count - deleted as i32))
             .get_result::<ArticleData>(conn)?;
+        let author = User::read(conn, article_data.author)?;
+        build_one(conn, article_data, author, Some(user_id))
+    })
+}
+
+/// Resets `favorites_count` to the number of rows in `favorites` for every
+/// article where the two have drifted apart. Returns the number of repaired
+/// articles.
+pub fn recount_favorites(conn: &PgConnection) -> Result<usize, Error> {
+    diesel::sql_query(
+        "UPDATE articles SET favorites_count = counts.n \
+         FROM (SELECT articles.id, count(favorites.article)::int AS n \
+               FROM articles LEFT JOIN favorites ON favorites.article = articles.id \
+               GROUP BY articles.id) AS counts \
+         WHERE articles.id = counts.id AND articles.favorites_count <> counts.n",
+    )
+    .execute(conn)
+}
+
+/// Adds the article at `slug` to the reading list of `user_id`, moving it
+/// to `folder` if it was already there.
+pub fn bookmark(
+    conn: &PgConnection,
+    user_id: i32,
+    slug: &str,
+    folder: Option<&str>,
+) -> Result<Article, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let article_id = visible_article_id(conn, slug, Some(user_id))?;
+        diesel::insert_into(bookmarks::table)
+            .values((
+                bookmarks::user.eq(user_id),
+                bookmarks::article.eq(article_id),
+                bookmarks::folder.eq(folder),
+            ))
+            .on_conflict((bookmarks::user, bookmarks::article))
+            .do_update()
+            .set(bookmarks::folder.eq(folder))
+            .execute(conn)?;
+        get_article(conn, slug, Some(user_id))
+    })
+}
+
+pub fn unbookmark(conn: &PgConnection, user_id: i32, slug: &str) -> Result<Article, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let article_id = visible_article_id(conn, slug, Some(user_id))?;
+        diesel::delete(bookmarks::table.find((user_id, article_id))).execute(conn)?;
+        get_article(conn, slug, Some(user_id))
+    })
+}
 
-        diesel::delete(favorites::table.find((user_id, article_data.id))).execute(conn)?;
+/// The reading list of `user_id`, most recently bookmarked first, optionally
+/// limited to one folder.
+pub fn bookmarks(
+    conn: &PgConnection,
+    user_id: i32,
+    folder: Option<&str>,
+    page: &Page,
+) -> Result<Articles, Error> {
+    let mut query = bookmarks::table
+        .inner_join(articles::table.inner_join(users::table))
+        .filter(bookmarks::user.eq(user_id))
+        .filter(articles::hidden_at.is_null())
+        .filter(articles::deleted_at.is_null())
+        .select((articles::all_columns, users::all_columns))
+        .into_boxed();
+    if let Some(folder) = folder {
+        query = query.filter(bookmarks::folder.eq(folder));
+    }
+    let rows = query
+        .order(bookmarks::created_at.desc())
+        .limit(page.limit())
+        .offset(page.offset())
+        .load::<(ArticleData, User)>(conn)?;
 
-        let p = Profile::find_follered(conn, user_id, article_data.author)?;
-        Ok(Article::build(article_data, p))
+    let batch = Batch::load(conn, Some(user_id), &rows)?;
+    let articles = build_all(rows, batch);
+    let articles_count = articles.len() as i64;
+    Ok(Articles {
+        articles,
+        articles_count,
     })
 }
 
-pub fn tag_list(conn: &PgConnection) -> Result<Vec<String>, Error> {
+pub fn article_tags(conn: &PgConnection, slug: &str) -> Result<Vec<String>, Error> {
     articles::table
-        .select(diesel::dsl::sql("distinct unnest(tag_list)"))
-        .load::<String>(conn)
+        .filter(articles::slug.eq(slug))
+        .filter(articles::deleted_at.is_null())
+        .select(articles::tag_list)
+        .first::<Vec<String>>(conn)
 }
@@ -0,0 +1,43 @@
+use crate::schema::*;
+use diesel::{prelude::*, result::Error};
+
+/// The blob holding the card drawn for `article` from `drawn_from`, a
+/// digest of what the card shows, if that has not changed since.
+pub fn find(conn: &PgConnection, article: i32, drawn_from: &str) -> Result<Option<String>, Error> {
+    article_cards::table
+        .find(article)
+        .filter(article_cards::drawn_from.eq(drawn_from))
+        .select(article_cards::hash)
+        .first::<String>(conn)
+        .optional()
+}
+
+/// Records `hash` as the card of `article` drawn from `drawn_from`.
+pub fn save(
+    conn: &PgConnection,
+    article: i32,
+    drawn_from: &str,
+    hash: &str,
+) -> Result<usize, Error> {
+    diesel::insert_into(article_cards::table)
+        .values((
+            article_cards::article.eq(article),
+            article_cards::hash.eq(hash),
+            article_cards::drawn_from.eq(drawn_from),
+        ))
+        .on_conflict(article_cards::article)
+        .do_update()
+        .set((
+            article_cards::hash.eq(hash),
+            article_cards::drawn_from.eq(drawn_from),
+        ))
+        .execute(conn)
+}
+
+/// Every blob hash that holds a card.
+pub fn hashes(conn: &PgConnection) -> Result<Vec<String>, Error> {
+    article_cards::table
+        .select(article_cards::hash)
+        .distinct()
+        .load::<String>(conn)
+}
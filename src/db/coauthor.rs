@@ -0,0 +1,143 @@
+use super::*;
+use crate::schema::*;
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, result::Error};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CoAuthors {
+    pub authors: Vec<CoAuthor>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CoAuthor {
+    pub profile: Profile,
+    pub position: i32,
+    pub invited_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Queryable)]
+struct CoAuthorData {
+    _article: i32,
+    _user: i32,
+    position: i32,
+    invited_at: DateTime<Utc>,
+    accepted_at: Option<DateTime<Utc>>,
+}
+
+impl CoAuthor {
+    fn build(data: CoAuthorData, profile: Profile) -> Self {
+        CoAuthor {
+            profile,
+            position: data.position,
+            invited_at: data.invited_at,
+            accepted_at: data.accepted_at,
+        }
+    }
+}
+
+/// Co-authors of the article at `slug` in order, including pending invitations.
+pub fn list(conn: &PgConnection, slug: &str, viewer: Option<i32>) -> Result<CoAuthors, Error> {
+    let rows = article_authors::table
+        .inner_join(articles::table)
+        .inner_join(users::table)
+        .filter(articles::slug.eq(slug))
+        .filter(articles::deleted_at.is_null())
+        .select((article_authors::all_columns, users::all_columns))
+        .order(article_authors::position)
+        .load::<(CoAuthorData, User)>(conn)?;
+
+    let users: Vec<i32> = rows.iter().map(|(_, u)| u.id).collect();
+    let viewer = Viewer::load(conn, viewer, &users, &[])?;
+    let authors = rows
+        .into_iter()
+        .map(|(data, user)| {
+            let following = viewer.follows(user.id);
+            CoAuthor::build(data, user.to_profile(following))
+        })
+        .collect();
+    Ok(CoAuthors { authors })
+}
+
+/// Invites `username` to co-author the article at `slug`, which `owner`
+/// must have written. Inviting someone twice keeps the first invitation.
+pub fn invite(
+    conn: &PgConnection,
+    slug: &str,
+    owner: i32,
+    username: &str,
+) -> Result<CoAuthor, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let article_id = articles::table
+            .filter(articles::slug.eq(slug).and(articles::author.eq(owner)))
+            .filter(articles::deleted_at.is_null())
+            .select(articles::id)
+            .for_update()
+            .first::<i32>(conn)?;
+        let user = User::with_username(conn, username)?;
+
+        let last = article_authors::table
+            .filter(article_authors::article.eq(article_id))
+            .select(diesel::dsl::max(article_authors::position))
+            .first::<Option<i32>>(conn)?;
+        diesel::insert_into(article_authors::table)
+            .values((
+                article_authors::article.eq(article_id),
+                article_authors::user.eq(user.id),
+                article_authors::position.eq(last.unwrap_or(0) + 1),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        let data = article_authors::table
+            .find((article_id, user.id))
+            .first::<CoAuthorData>(conn)?;
+        let following = Profile::is_following(conn, user.id, owner)?;
+        Ok(CoAuthor::build(data, user.to_profile(following)))
+    })
+}
+
+/// Accepts a pending invitation of `user` to the article at `slug`.
+pub fn accept(conn: &PgConnection, slug: &str, user: i32) -> Result<usize, Error> {
+    let article_id = articles::table
+        .filter(articles::slug.eq(slug))
+        .filter(articles::deleted_at.is_null())
+        .select(articles::id)
+        .first::<i32>(conn)?;
+    let updated = diesel::update(
+        article_authors::table
+            .find((article_id, user))
+            .filter(article_authors::accepted_at.is_null()),
+    )
+    .set(article_authors::accepted_at.eq(Some(Utc::now())))
+    .execute(conn)?;
+    if updated == 0 {
+        // Already accepted is fine; never invited is not.
+        article_authors::table
+            .find((article_id, user))
+            .select(article_authors::position)
+            .first::<i32>(conn)?;
+    } else {
+        sitemap::article(conn, article_id)?;
+    }
+    Ok(updated)
+}
+
+/// Removes `username` from the co-authors of the article at `slug`. The
+/// article's author may remove anyone; co-authors may only remove themselves.
+pub fn remove(conn: &PgConnection, slug: &str, actor: i32, username: &str) -> Result<usize, Error> {
+    let (article_id, author) = articles::table
+        .filter(articles::slug.eq(slug))
+        .filter(articles::deleted_at.is_null())
+        .select((articles::id, articles::author))
+        .first::<(i32, i32)>(conn)?;
+    let user = User::with_username(conn, username)?;
+    if actor != author && actor != user.id {
+        return Err(Error::NotFound);
+    }
+    let removed = diesel::delete(article_authors::table.find((article_id, user.id))).execute(conn)?;
+    sitemap::prune(conn, user.id, &[])?;
+    Ok(removed)
+}
@@ -1,5 +1,5 @@
 use crate::{
-    db::{Crud, Profile, User},
+    db::{article, reaction, Crud, Profile, User, Viewer},
     schema::*,
 };
 use chrono::{DateTime, Utc};
@@ -18,7 +18,15 @@ pub struct Comment {
     pub body: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub reactions: reaction::Counts,
+    pub my_reactions: Vec<String>,
     pub author: Profile,
+    /// Only ever shown to the author, as nobody else sees hidden comments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hidden_at: Option<DateTime<Utc>>,
+    /// Only set on comments in the trash.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Queryable, Clone)]
@@ -29,6 +37,11 @@ pub struct CommentData {
     pub author: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub reaction_counts: serde_json::Value,
+    pub hidden_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<i32>,
+    pub removed_by: Option<i32>,
 }
 
 impl Comment {
@@ -38,9 +51,18 @@ impl Comment {
             body: comment.body,
             created_at: comment.created_at,
             updated_at: comment.updated_at,
+            reactions: reaction::counts(comment.reaction_counts),
+            my_reactions: Vec::new(),
             author: user,
+            hidden_at: comment.hidden_at,
+            deleted_at: comment.deleted_at,
         }
     }
+
+    fn my_reactions(mut self, reactions: Vec<String>) -> Self {
+        self.my_reactions = reactions;
+        self
+    }
 }
 
 pub fn add_comment(
@@ -49,10 +71,7 @@ pub fn add_comment(
     slug: &str,
     body: &str,
 ) -> Result<Comment, Error> {
-    let article_id = articles::table
-        .filter(articles::slug.eq(slug))
-        .select(articles::id)
-        .get_result::<i32>(conn)?;
+    let article_id = article::visible_article_id(conn, slug, Some(user))?;
 
     let comment_data = diesel::insert_into(comments::table)
         .values((
@@ -66,43 +85,177 @@ pub fn add_comment(
     Ok(Comment::build(comment_data, user))
 }
 
-pub fn get_comments(conn: &PgConnection, user_id: i32, slug: &str) -> Result<Comments, Error> {
-    let comments = comments::table
-        .inner_join(
-            articles::table.on(comments::article
-                .eq(articles::id)
-                .and(articles::slug.eq(slug))),
-        )
-        .left_join(users::table.on(articles::author.eq(users::id)))
-        .left_join(
-            follows::table.on(users::id
-                .eq(follows::followed)
-                .and(follows::follower.eq(user_id))),
-        )
-        .select((
-            comments::all_columns,
-            users::all_columns,
-            follows::follower.nullable().is_not_null(),
-        ))
-        .load::<(CommentData, User, bool)>(conn)?
+fn build_all(
+    conn: &PgConnection,
+    rows: Vec<(CommentData, User)>,
+    viewer: Option<i32>,
+) -> Result<Vec<Comment>, Error> {
+    let authors: Vec<i32> = rows.iter().map(|(c, _)| c.author).collect();
+    let ids: Vec<i32> = rows.iter().map(|(c, _)| c.id).collect();
+    let mut viewer = Viewer::load(conn, viewer, &authors, &[])?;
+    viewer.load_comments(conn, &ids)?;
+    Ok(rows
         .into_iter()
-        .map(|(c, u, f)| Comment::build(c, u.to_profile(f)))
-        .collect();
-    Ok(Comments { comments })
+        .map(|(c, u)| {
+            let following = viewer.follows(u.id);
+            let my_reactions = viewer.comment_reactions(c.id);
+            Comment::build(c, u.to_profile(following)).my_reactions(my_reactions)
+        })
+        .collect())
+}
+
+pub fn get_comment(
+    conn: &PgConnection,
+    viewer: Option<i32>,
+    slug: &str,
+    comment_id: i32,
+) -> Result<Comment, Error> {
+    let article_id = article::visible_article_id(conn, slug, viewer)?;
+    let row = comments::table
+        .inner_join(users::table.on(comments::author.eq(users::id)))
+        .filter(comments::article.eq(article_id))
+        .filter(comments::id.eq(comment_id))
+        .filter(comments::deleted_at.is_null())
+        .select((comments::all_columns, users::all_columns))
+        .first::<(CommentData, User)>(conn)?;
+    if row.0.hidden_at.is_some() && viewer != Some(row.0.author) {
+        return Err(Error::NotFound);
+    }
+    Ok(build_all(conn, vec![row], viewer)?.remove(0))
+}
+
+pub fn get_comments(conn: &PgConnection, viewer: Option<i32>, slug: &str) -> Result<Comments, Error> {
+    let article_id = article::visible_article_id(conn, slug, viewer)?;
+    let mut query = comments::table
+        .inner_join(users::table.on(comments::author.eq(users::id)))
+        .filter(comments::article.eq(article_id))
+        .filter(comments::deleted_at.is_null())
+        .select((comments::all_columns, users::all_columns))
+        .into_boxed();
+    query = match viewer {
+        Some(viewer) => query.filter(
+            comments::hidden_at
+                .is_null()
+                .or(comments::author.eq(viewer)),
+        ),
+        None => query.filter(comments::hidden_at.is_null()),
+    };
+    let rows = query
+        .order(comments::id)
+        .load::<(CommentData, User)>(conn)?;
+
+    Ok(Comments {
+        comments: build_all(conn, rows, viewer)?,
+    })
 }
 
+/// Moves a comment to the trash of `user_id`, who must have written either
+/// the comment or the article.
 pub fn delete_comment(
     conn: &PgConnection,
     user_id: i32,
     slug: &str,
     comment_id: i32,
 ) -> Result<usize, Error> {
-    diesel::select(exists(
-        articles::table.filter(articles::slug.eq(slug).and(articles::author.eq(user_id))),
-    ))
-    .get_result::<bool>(conn)?;
-
-    diesel::delete(comments::table)
+    let on_own_article = articles::table
+        .select(articles::id)
+        .filter(articles::slug.eq(slug).and(articles::author.eq(user_id)));
+    diesel::update(comments::table)
         .filter(comments::id.eq(comment_id))
+        .filter(comments::deleted_at.is_null())
+        .filter(
+            comments::author
+                .eq(user_id)
+                .or(comments::article.eq_any(on_own_article)),
+        )
+        .filter(exists(
+            articles::table
+                .filter(articles::id.eq(comments::article))
+                .filter(articles::slug.eq(slug))
+                .filter(articles::deleted_at.is_null()),
+        ))
+        .set((
+            comments::deleted_at.eq(Utc::now()),
+            comments::deleted_by.eq(user_id),
+        ))
         .execute(conn)
 }
+
+/// Takes a comment `user_id` deleted back out of the trash. The article
+/// must not be in the trash itself.
+pub fn restore(
+    conn: &PgConnection,
+    user_id: i32,
+    slug: &str,
+    comment_id: i32,
+) -> Result<Comment, Error> {
+    diesel::update(comments::table)
+        .filter(comments::id.eq(comment_id))
+        .filter(comments::deleted_by.eq(user_id))
+        .filter(comments::deleted_at.is_not_null())
+        .filter(exists(
+            articles::table
+                .filter(articles::id.eq(comments::article))
+                .filter(articles::slug.eq(slug))
+                .filter(articles::deleted_at.is_null()),
+        ))
+        .set((
+            comments::deleted_at.eq(None::<DateTime<Utc>>),
+            comments::deleted_by.eq(None::<i32>),
+            comments::removed_by.eq(None::<i32>),
+        ))
+        .get_result::<CommentData>(conn)?;
+    get_comment(conn, Some(user_id), slug, comment_id)
+}
+
+/// A comment in the trash, with the article it was posted on.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedComment {
+    #[serde(flatten)]
+    pub comment: Comment,
+    pub article_slug: String,
+}
+
+/// The comments `user_id` deleted, most recently deleted first.
+pub fn trash(conn: &PgConnection, user_id: i32) -> Result<Vec<TrashedComment>, Error> {
+    let rows = comments::table
+        .inner_join(articles::table)
+        .inner_join(users::table.on(comments::author.eq(users::id)))
+        .filter(comments::deleted_by.eq(user_id))
+        .filter(comments::deleted_at.is_not_null())
+        .select((comments::all_columns, users::all_columns, articles::slug))
+        .order(comments::deleted_at.desc())
+        .load::<(CommentData, User, String)>(conn)?;
+    let slugs: Vec<String> = rows.iter().map(|(_, _, slug)| slug.clone()).collect();
+    let rows = rows.into_iter().map(|(c, u, _)| (c, u)).collect();
+    Ok(build_all(conn, rows, Some(user_id))?
+        .into_iter()
+        .zip(slugs)
+        .map(|(comment, article_slug)| TrashedComment {
+            comment,
+            article_slug,
+        })
+        .collect())
+}
+
+/// Deletes comments that have been in the trash since before `before`.
+pub fn purge(conn: &PgConnection, before: DateTime<Utc>) -> Result<usize, Error> {
+    diesel::delete(comments::table.filter(comments::deleted_at.lt(before))).execute(conn)
+}
+
+/// Moves a comment to the trash of its author on behalf of `moderator`, who
+/// is recorded as having removed it.
+pub fn remove(conn: &PgConnection, comment_id: i32, moderator: i32) -> Result<usize, Error> {
+    diesel::update(
+        comments::table
+            .find(comment_id)
+            .filter(comments::deleted_at.is_null()),
+    )
+    .set((
+        comments::deleted_at.eq(Utc::now()),
+        comments::deleted_by.eq(comments::author.nullable()),
+        comments::removed_by.eq(moderator),
+    ))
+    .execute(conn)
+}
@@ -0,0 +1,61 @@
+use super::*;
+use crate::schema::*;
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, result::Error};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Favorites {
+    pub favorites: Vec<Favorite>,
+    pub favorites_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Favorite {
+    pub profile: Profile,
+    pub favorited_at: DateTime<Utc>,
+}
+
+/// Users who favorited the article at `slug`, most recent first.
+pub fn list(
+    conn: &PgConnection,
+    slug: &str,
+    page: &Page,
+    viewer: Option<i32>,
+) -> Result<Favorites, Error> {
+    let article_id = article::visible_article_id(conn, slug, viewer)?;
+
+    let favorites_count = favorites::table
+        .filter(favorites::article.eq(article_id))
+        .count()
+        .get_result::<i64>(conn)?;
+
+    let rows = favorites::table
+        .inner_join(users::table)
+        .filter(favorites::article.eq(article_id))
+        .select((users::all_columns, favorites::created_at))
+        .order((favorites::created_at.desc(), favorites::user))
+        .limit(page.limit())
+        .offset(page.offset())
+        .load::<(User, DateTime<Utc>)>(conn)?;
+
+    let users: Vec<i32> = rows.iter().map(|(u, _)| u.id).collect();
+    let viewer = Viewer::load(conn, viewer, &users, &[])?;
+    let favorites = rows
+        .into_iter()
+        .map(|(user, favorited_at)| {
+            let following = viewer.follows(user.id);
+            Favorite {
+                profile: user.to_profile(following),
+                favorited_at,
+            }
+        })
+        .collect();
+
+    Ok(Favorites {
+        favorites,
+        favorites_count,
+    })
+}
@@ -0,0 +1,29 @@
+use crate::schema::*;
+use diesel::{prelude::*, result::Error};
+
+/// The secret that authorizes the personal feed URL of `user`, created on
+/// first use.
+pub fn get(conn: &PgConnection, user: i32) -> Result<String, Error> {
+    diesel::insert_into(feed_tokens::table)
+        .values(feed_tokens::user.eq(user))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    feed_tokens::table
+        .find(user)
+        .select(feed_tokens::token)
+        .first::<String>(conn)
+}
+
+/// Replaces the token of `user`, so feed URLs shared before stop working.
+pub fn regenerate(conn: &PgConnection, user: i32) -> Result<String, Error> {
+    diesel::delete(feed_tokens::table.find(user)).execute(conn)?;
+    get(conn, user)
+}
+
+/// The user a personal feed `token` belongs to.
+pub fn user(conn: &PgConnection, token: &str) -> Result<i32, Error> {
+    feed_tokens::table
+        .filter(feed_tokens::token.eq(token))
+        .select(feed_tokens::user)
+        .first::<i32>(conn)
+}
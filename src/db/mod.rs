@@ -1,14 +1,52 @@
 use diesel::pg::PgConnection;
 use diesel::result::Error;
+use serde::Deserialize;
 
+pub mod activity;
+pub mod archive;
 pub mod article;
+pub mod card;
+pub mod coauthor;
 pub mod comment;
+pub mod favorite;
+pub mod feed_token;
 pub mod profile;
+pub mod reaction;
+pub mod related;
+pub mod report;
+pub mod revision;
+pub mod series;
+pub mod sitemap;
+pub mod stats;
+pub mod tag;
+pub mod upload;
 pub mod user;
+pub mod viewer;
+pub mod warning;
 
 pub use article::{Article, ArticleForm};
 pub use profile::Profile;
-pub use user::{User, UserForm};
+pub use revision::{Revision, RevisionForm};
+pub use tag::Tag;
+pub use user::{Avatar, User, UserForm};
+pub use viewer::Viewer;
+
+/// `limit`/`offset` query parameters shared by paginated listings.
+#[derive(Debug, Default, Deserialize)]
+pub struct Page {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl Page {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(20)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0)
+    }
+}
 
 pub trait Crud<T> {
     fn create(conn: &PgConnection, form: &T) -> Result<Self, Error>
@@ -3,11 +3,12 @@ use crate::schema::*;
 use diesel::{dsl::exists, prelude::*, result::Error};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Profile {
     pub username: String,
     pub bio: Option<String>,
     pub image: Option<String>,
+    pub avatar: Avatar,
     pub following: bool,
 }
 
@@ -25,34 +26,11 @@ impl Profile {
             .map(|id| Profile::is_following(conn, user.id, id).unwrap_or(false))
             .unwrap_or(false);
 
-        Ok(Profile {
-            username: user.username,
-            bio: user.bio,
-            image: user.image,
-            following,
-        })
+        Ok(user.to_profile(following))
     }
 
-    pub fn find_follered(conn: &PgConnection, follower: i32, followed: i32) -> Result<Self, Error> {
-        let p: Profile = users::table
-            .left_join(
-                follows::table.on(follows::followed.eq(users::id).and(
-                    follows::followed
-                        .eq(followed)
-                        .and(follows::follower.eq(follower)),
-                )),
-            )
-            .select((
-                users::all_columns,
-                follows::followed.nullable().is_not_null(),
-            ))
-            .get_result::<(User, bool)>(conn)
-            .map(|(user, following)| user.to_profile(following))?;
-        Ok(p)
-    }
-
-    fn is_following(conn: &PgConnection, followed: i32, follower: i32) -> Result<bool, Error> {
-        let f = diesel::select(exists(follows::table.find((followed, follower))))
+    pub fn is_following(conn: &PgConnection, followed: i32, follower: i32) -> Result<bool, Error> {
+        let f = diesel::select(exists(follows::table.find((follower, followed))))
             .get_result::<bool>(conn)?;
         Ok(f)
     }
@@ -64,6 +42,7 @@ impl Profile {
                 follows::follower.eq(follower),
                 follows::followed.eq(followed.id),
             ))
+            .on_conflict_do_nothing()
             .execute(conn)?;
 
         Ok(followed.to_profile(true))
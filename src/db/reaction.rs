@@ -0,0 +1,120 @@
+use crate::{db::article, schema::*};
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+    result::Error,
+    sql_types::{Int4, Text},
+};
+use std::collections::BTreeMap;
+
+/// Number of reactions per emoji, as stored in `reaction_counts`.
+pub type Counts = BTreeMap<String, i64>;
+
+pub fn counts(value: serde_json::Value) -> Counts {
+    serde_json::from_value(value).unwrap_or_default()
+}
+
+/// Adds `delta` to the count of `reaction` on row `id` of `table`, dropping
+/// the key once it reaches zero.
+fn bump(conn: &PgConnection, table: &str, id: i32, reaction: &str, delta: i32) -> Result<usize, Error> {
+    diesel::sql_query(format!(
+        "UPDATE {table} SET reaction_counts = CASE \
+             WHEN coalesce((reaction_counts->>$2)::int, 0) + $3 <= 0 THEN reaction_counts - $2 \
+             ELSE jsonb_set(reaction_counts, ARRAY[$2], \
+                            to_jsonb(coalesce((reaction_counts->>$2)::int, 0) + $3)) \
+         END \
+         WHERE id = $1",
+        table = table
+    ))
+    .bind::<Int4, _>(id)
+    .bind::<Text, _>(reaction)
+    .bind::<Int4, _>(delta)
+    .execute(conn)
+}
+
+/// Adds `reaction` by `user` to the article at `slug`, or takes it back if
+/// it was already there.
+pub fn toggle_article(conn: &PgConnection, user: i32, slug: &str, reaction: &str) -> Result<(), Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let article_id = article::visible_article_id(conn, slug, Some(user))?;
+        articles::table
+            .find(article_id)
+            .select(articles::id)
+            .for_update()
+            .first::<i32>(conn)?;
+        let deleted =
+            diesel::delete(article_reactions::table.find((article_id, user, reaction))).execute(conn)?;
+        if deleted == 0 {
+            diesel::insert_into(article_reactions::table)
+                .values((
+                    article_reactions::article.eq(article_id),
+                    article_reactions::user.eq(user),
+                    article_reactions::reaction.eq(reaction),
+                ))
+                .execute(conn)?;
+        }
+        bump(conn, "articles", article_id, reaction, if deleted == 0 { 1 } else { -1 })?;
+        Ok(())
+    })
+}
+
+/// Adds `reaction` by `user` to comment `comment_id` under the article at
+/// `slug`, or takes it back if it was already there.
+pub fn toggle_comment(
+    conn: &PgConnection,
+    user: i32,
+    slug: &str,
+    comment_id: i32,
+    reaction: &str,
+) -> Result<(), Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let article_id = article::visible_article_id(conn, slug, Some(user))?;
+        let comment_id = comments::table
+            .filter(comments::article.eq(article_id))
+            .filter(comments::id.eq(comment_id))
+            .filter(comments::deleted_at.is_null())
+            .filter(comments::hidden_at.is_null().or(comments::author.eq(user)))
+            .select(comments::id)
+            .for_update()
+            .first::<i32>(conn)?;
+        let deleted =
+            diesel::delete(comment_reactions::table.find((comment_id, user, reaction))).execute(conn)?;
+        if deleted == 0 {
+            diesel::insert_into(comment_reactions::table)
+                .values((
+                    comment_reactions::comment.eq(comment_id),
+                    comment_reactions::user.eq(user),
+                    comment_reactions::reaction.eq(reaction),
+                ))
+                .execute(conn)?;
+        }
+        bump(conn, "comments", comment_id, reaction, if deleted == 0 { 1 } else { -1 })?;
+        Ok(())
+    })
+}
+
+/// Rebuilds `reaction_counts` of articles and comments from the reaction
+/// tables wherever they have drifted apart. Returns the number of repaired rows.
+pub fn recount(conn: &PgConnection) -> Result<usize, Error> {
+    let mut repaired = 0;
+    for (table, reactions, key) in &[
+        ("articles", "article_reactions", "article"),
+        ("comments", "comment_reactions", "comment"),
+    ] {
+        repaired += diesel::sql_query(format!(
+            "UPDATE {table} SET reaction_counts = counts.n \
+             FROM (SELECT {table}.id, coalesce(jsonb_object_agg(r.reaction, r.n) \
+                                               FILTER (WHERE r.reaction IS NOT NULL), '{{}}') AS n \
+                   FROM {table} LEFT JOIN (SELECT {key}, reaction, count(*) AS n \
+                                           FROM {reactions} GROUP BY 1, 2) AS r \
+                                 ON r.{key} = {table}.id \
+                   GROUP BY {table}.id) AS counts \
+             WHERE {table}.id = counts.id AND {table}.reaction_counts <> counts.n",
+            table = table,
+            reactions = reactions,
+            key = key
+        ))
+        .execute(conn)?;
+    }
+    Ok(repaired)
+}
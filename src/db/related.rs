@@ -0,0 +1,96 @@
+use crate::schema::*;
+use chrono::{Duration, Utc};
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+    result::Error,
+    sql_types::{Float8, Int4, Int8},
+};
+
+/// How many candidates are kept per article.
+const CACHED: i64 = 50;
+/// How long a cached list is used before favorites are taken into account again.
+const TTL_HOURS: i64 = 24;
+
+const TAG_WEIGHT: f64 = 3.0;
+const FAVORITER_WEIGHT: f64 = 1.0;
+const AUTHOR_WEIGHT: f64 = 2.0;
+
+/// Recomputes the related articles of `article_id` unless a fresh list is
+/// cached. Articles score for every shared tag, every user who favorited
+/// both, and for having the same author.
+pub fn refresh(conn: &PgConnection, article_id: i32) -> Result<(), Error> {
+    conn.transaction::<_, Error, _>(|| {
+        // The cache row may not exist yet, so lock the article's slot rather
+        // than the row to keep concurrent readers from recomputing together.
+        diesel::sql_query("SELECT pg_advisory_xact_lock('related_cache'::regclass::int, $1)")
+            .bind::<Int4, _>(article_id)
+            .execute(conn)?;
+        let fresh = related_cache::table
+            .find(article_id)
+            .filter(related_cache::computed_at.gt(Utc::now() - Duration::hours(TTL_HOURS)))
+            .select(related_cache::article)
+            .first::<i32>(conn)
+            .optional()?;
+        if fresh.is_some() {
+            return Ok(());
+        }
+
+        diesel::delete(related_articles::table.filter(related_articles::article.eq(article_id)))
+            .execute(conn)?;
+        diesel::sql_query(
+            "INSERT INTO related_articles (article, related, score) \
+             SELECT me.id, a.id, \
+                    $2 * cardinality(ARRAY(SELECT unnest(a.tag_list) \
+                                           INTERSECT SELECT unnest(me.tag_list))) \
+                  + $3 * (SELECT count(*) FROM favorites f \
+                          WHERE f.article = a.id AND f.\"user\" IN \
+                                (SELECT \"user\" FROM favorites WHERE article = me.id)) \
+                  + CASE WHEN a.author = me.author THEN $4 ELSE 0 END AS score \
+             FROM articles a, articles me \
+             WHERE me.id = $1 AND a.id <> me.id AND a.deleted_at IS NULL \
+               AND (a.tag_list && me.tag_list OR a.author = me.author \
+                    OR EXISTS (SELECT 1 FROM favorites f \
+                               WHERE f.article = a.id AND f.\"user\" IN \
+                                     (SELECT \"user\" FROM favorites WHERE article = me.id))) \
+             ORDER BY score DESC, a.created_at DESC \
+             LIMIT $5",
+        )
+        .bind::<Int4, _>(article_id)
+        .bind::<Float8, _>(TAG_WEIGHT)
+        .bind::<Float8, _>(FAVORITER_WEIGHT)
+        .bind::<Float8, _>(AUTHOR_WEIGHT)
+        .bind::<Int8, _>(CACHED)
+        .execute(conn)?;
+
+        diesel::insert_into(related_cache::table)
+            .values(related_cache::article.eq(article_id))
+            .on_conflict(related_cache::article)
+            .do_update()
+            .set(related_cache::computed_at.eq(Utc::now()))
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+/// Drops cached lists that the tags of `article_id` may have changed: its
+/// own, those it appears in, and those of articles sharing one of `tags`.
+pub fn invalidate(conn: &PgConnection, article_id: i32, tags: &[String]) -> Result<usize, Error> {
+    diesel::delete(
+        related_cache::table.filter(
+            related_cache::article
+                .eq(article_id)
+                .or(related_cache::article.eq_any(
+                    related_articles::table
+                        .select(related_articles::article)
+                        .filter(related_articles::related.eq(article_id)),
+                ))
+                .or(related_cache::article.eq_any(
+                    articles::table
+                        .select(articles::id)
+                        .filter(articles::tag_list.overlaps_with(tags)),
+                )),
+        ),
+    )
+    .execute(conn)
+}
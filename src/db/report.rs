@@ -0,0 +1,444 @@
+use super::{article, comment, sitemap, warning, Page, User};
+use crate::schema::*;
+use chrono::{DateTime, Utc};
+use diesel::{dsl::sql, prelude::*, result::Error, sql_types::BigInt};
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub const ARTICLE: &str = "article";
+pub const COMMENT: &str = "comment";
+pub const PROFILE: &str = "profile";
+pub const TARGET_TYPES: [&str; 3] = [ARTICLE, COMMENT, PROFILE];
+
+pub const REASONS: [&str; 7] = [
+    "spam",
+    "harassment",
+    "hate",
+    "sexual",
+    "violence",
+    "misinformation",
+    "other",
+];
+
+pub const OPEN: &str = "open";
+pub const ACTIONED: &str = "actioned";
+pub const DISMISSED: &str = "dismissed";
+pub const STATUSES: [&str; 3] = [OPEN, ACTIONED, DISMISSED];
+
+pub const HIDE: &str = "hide";
+pub const REMOVE: &str = "remove";
+pub const WARN: &str = "warn";
+pub const SUSPEND: &str = "suspend";
+pub const DISMISS: &str = "dismiss";
+pub const ACTIONS: [&str; 5] = [HIDE, REMOVE, WARN, SUSPEND, DISMISS];
+
+#[derive(Insertable)]
+#[table_name = "reports"]
+pub struct NewReport<'a> {
+    /// `None` for reports filed automatically.
+    pub reporter: Option<i32>,
+    pub target_type: &'a str,
+    pub target_id: i32,
+    pub reason: &'a str,
+    pub details: &'a str,
+}
+
+#[derive(Queryable)]
+struct ReportData {
+    id: i32,
+    reporter: Option<i32>,
+    target_type: String,
+    target_id: i32,
+    reason: String,
+    details: String,
+    status: String,
+    action: Option<String>,
+    resolved_by: Option<i32>,
+    resolved_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+/// What a report is about, for moderators to judge it without looking it up.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    pub author: String,
+    /// The article, or the article commented on.
+    pub slug: Option<String>,
+    /// The title of an article, the body of a comment or the bio of a profile.
+    pub text: String,
+    pub hidden_at: Option<DateTime<Utc>>,
+    pub author_suspended_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub id: i32,
+    pub target_type: String,
+    pub target_id: i32,
+    /// `None` once the target was removed.
+    pub target: Option<Target>,
+    /// Open reports on the same target, this one included.
+    pub open_reports: i64,
+    pub reason: String,
+    pub details: String,
+    pub status: String,
+    pub action: Option<String>,
+    pub reporter: Option<String>,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Looks up what a user reports: an article by slug, a comment by id or a
+/// profile by username. Returns its id and the id of its author.
+pub fn find_target(conn: &PgConnection, target_type: &str, key: &str) -> Result<(i32, i32), Error> {
+    match target_type {
+        ARTICLE => articles::table
+            .filter(articles::slug.eq(key))
+            .filter(articles::deleted_at.is_null())
+            .select((articles::id, articles::author))
+            .first(conn),
+        COMMENT => comments::table
+            .inner_join(articles::table)
+            .filter(comments::id.eq(key.parse::<i32>().map_err(|_| Error::NotFound)?))
+            .filter(comments::deleted_at.is_null())
+            .filter(articles::deleted_at.is_null())
+            .select((comments::id, comments::author))
+            .first(conn),
+        PROFILE => users::table
+            .filter(users::username.eq(key))
+            .select((users::id, users::id))
+            .first(conn),
+        _ => Err(Error::NotFound),
+    }
+}
+
+/// The author of a target, for warnings and suspensions.
+pub fn owner(conn: &PgConnection, target_type: &str, target_id: i32) -> Result<i32, Error> {
+    match target_type {
+        ARTICLE => articles::table
+            .find(target_id)
+            .select(articles::author)
+            .first(conn),
+        COMMENT => comments::table
+            .find(target_id)
+            .select(comments::author)
+            .first(conn),
+        _ => Ok(target_id),
+    }
+}
+
+/// Hides an article or comment from everyone but its author, or shows it
+/// again. Profiles cannot be hidden, only their owners suspended.
+pub fn set_hidden(
+    conn: &PgConnection,
+    target_type: &str,
+    target_id: i32,
+    hidden: bool,
+) -> Result<(), Error> {
+    let hidden_at = if hidden { Some(Utc::now()) } else { None };
+    match target_type {
+        ARTICLE => {
+            // Keep the time an already hidden article was hidden at.
+            let mut query = diesel::update(articles::table.find(target_id)).into_boxed();
+            if hidden {
+                query = query.filter(articles::hidden_at.is_null());
+            }
+            query.set(articles::hidden_at.eq(hidden_at)).execute(conn)?;
+            sitemap::article(conn, target_id)
+        }
+        COMMENT => {
+            let mut query = diesel::update(comments::table.find(target_id)).into_boxed();
+            if hidden {
+                query = query.filter(comments::hidden_at.is_null());
+            }
+            query.set(comments::hidden_at.eq(hidden_at)).execute(conn)?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn open_on(target_type: &str, target_id: i32) -> reports::BoxedQuery<'_, diesel::pg::Pg> {
+    reports::table
+        .filter(reports::target_type.eq(target_type))
+        .filter(reports::target_id.eq(target_id))
+        .filter(reports::status.eq(OPEN))
+        .into_boxed()
+}
+
+/// Files a report. Once `hide_after` different users have open reports on
+/// an article or comment, it is hidden until a moderator looks at it; zero
+/// turns that off.
+pub fn create(conn: &PgConnection, report: &NewReport, hide_after: i64) -> Result<Report, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let id = diesel::insert_into(reports::table)
+            .values(report)
+            .returning(reports::id)
+            .get_result::<i32>(conn)?;
+        if hide_after > 0 && report.target_type != PROFILE {
+            let reporters = open_on(report.target_type, report.target_id)
+                .select(sql::<BigInt>("count(DISTINCT reporter)"))
+                .first::<i64>(conn)?;
+            if reporters >= hide_after {
+                set_hidden(conn, report.target_type, report.target_id, true)?;
+            }
+        }
+        get(conn, id)
+    })
+}
+
+/// Hides an article or comment a content filter flagged and files a report
+/// for moderators to review it. Dismissing the report publishes it.
+pub fn hold(
+    conn: &PgConnection,
+    target_type: &str,
+    target_id: i32,
+    reason: &str,
+) -> Result<(), Error> {
+    set_hidden(conn, target_type, target_id, true)?;
+    diesel::insert_into(reports::table)
+        .values(&NewReport {
+            reporter: None,
+            target_type,
+            target_id,
+            reason: "spam",
+            details: reason,
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+fn targets(
+    conn: &PgConnection,
+    rows: &[ReportData],
+) -> Result<HashMap<(String, i32), Target>, Error> {
+    let ids = |target_type: &str| -> Vec<i32> {
+        rows.iter()
+            .filter(|r| r.target_type == target_type)
+            .map(|r| r.target_id)
+            .collect()
+    };
+    let mut targets = HashMap::new();
+
+    let articles = articles::table
+        .inner_join(users::table)
+        .filter(articles::id.eq_any(ids(ARTICLE)))
+        .select((
+            articles::id,
+            users::username,
+            articles::slug,
+            articles::title,
+            articles::hidden_at,
+            users::suspended_at,
+        ))
+        .load::<(
+            i32,
+            String,
+            String,
+            String,
+            Option<DateTime<Utc>>,
+            Option<DateTime<Utc>>,
+        )>(conn)?;
+    for (id, author, slug, title, hidden_at, author_suspended_at) in articles {
+        let target = Target {
+            author,
+            slug: Some(slug),
+            text: title,
+            hidden_at,
+            author_suspended_at,
+        };
+        targets.insert((ARTICLE.to_string(), id), target);
+    }
+
+    let comments = comments::table
+        .inner_join(articles::table)
+        .inner_join(users::table.on(users::id.eq(comments::author)))
+        .filter(comments::id.eq_any(ids(COMMENT)))
+        .select((
+            comments::id,
+            users::username,
+            articles::slug,
+            comments::body,
+            comments::hidden_at,
+            users::suspended_at,
+        ))
+        .load::<(
+            i32,
+            String,
+            String,
+            String,
+            Option<DateTime<Utc>>,
+            Option<DateTime<Utc>>,
+        )>(conn)?;
+    for (id, author, slug, body, hidden_at, author_suspended_at) in comments {
+        let target = Target {
+            author,
+            slug: Some(slug),
+            text: body,
+            hidden_at,
+            author_suspended_at,
+        };
+        targets.insert((COMMENT.to_string(), id), target);
+    }
+
+    let profiles = users::table
+        .filter(users::id.eq_any(ids(PROFILE)))
+        .select((users::id, users::username, users::bio, users::suspended_at))
+        .load::<(i32, String, Option<String>, Option<DateTime<Utc>>)>(conn)?;
+    for (id, author, bio, author_suspended_at) in profiles {
+        let target = Target {
+            author,
+            slug: None,
+            text: bio.unwrap_or_default(),
+            hidden_at: None,
+            author_suspended_at,
+        };
+        targets.insert((PROFILE.to_string(), id), target);
+    }
+    Ok(targets)
+}
+
+fn build_all(conn: &PgConnection, rows: Vec<(ReportData, i64)>) -> Result<Vec<Report>, Error> {
+    let (rows, counts): (Vec<ReportData>, Vec<i64>) = rows.into_iter().unzip();
+    let mut targets = targets(conn, &rows)?;
+    let user_ids: Vec<i32> = rows
+        .iter()
+        .flat_map(|r| r.reporter.into_iter().chain(r.resolved_by))
+        .collect();
+    let usernames: HashMap<i32, String> = users::table
+        .filter(users::id.eq_any(user_ids))
+        .select((users::id, users::username))
+        .load::<(i32, String)>(conn)?
+        .into_iter()
+        .collect();
+    let username = |id: Option<i32>| id.and_then(|id| usernames.get(&id).cloned());
+
+    Ok(rows
+        .into_iter()
+        .zip(counts)
+        .map(|(r, open_reports)| Report {
+            target: targets.remove(&(r.target_type.clone(), r.target_id)),
+            reporter: username(r.reporter),
+            resolved_by: username(r.resolved_by),
+            id: r.id,
+            target_type: r.target_type,
+            target_id: r.target_id,
+            open_reports,
+            reason: r.reason,
+            details: r.details,
+            status: r.status,
+            action: r.action,
+            resolved_at: r.resolved_at,
+            created_at: r.created_at,
+        })
+        .collect())
+}
+
+fn open_count() -> diesel::expression::SqlLiteral<BigInt> {
+    sql::<BigInt>(
+        "(SELECT count(*) FROM reports AS other \
+         WHERE other.target_type = reports.target_type \
+         AND other.target_id = reports.target_id AND other.status = 'open')",
+    )
+}
+
+pub fn get(conn: &PgConnection, id: i32) -> Result<Report, Error> {
+    let row = reports::table
+        .find(id)
+        .select((reports::all_columns, open_count()))
+        .first::<(ReportData, i64)>(conn)?;
+    Ok(build_all(conn, vec![row])?.remove(0))
+}
+
+/// The moderation queue: reports with `status`, oldest first so nothing
+/// waits forever.
+pub fn list(conn: &PgConnection, status: &str, page: &Page) -> Result<Vec<Report>, Error> {
+    let rows = reports::table
+        .filter(reports::status.eq(status))
+        .select((reports::all_columns, open_count()))
+        .order(reports::id)
+        .limit(page.limit())
+        .offset(page.offset())
+        .load::<(ReportData, i64)>(conn)?;
+    build_all(conn, rows)
+}
+
+/// Settles report `id` along with every other open report on the same
+/// target. `action` is one of `ACTIONS`; dismissing shows hidden content
+/// again unless a moderator hid it before. `message` is sent to the author
+/// as a warning.
+pub fn resolve(
+    conn: &PgConnection,
+    id: i32,
+    moderator: i32,
+    action: &str,
+    message: Option<&str>,
+) -> Result<Report, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let (target_type, target_id) = reports::table
+            .find(id)
+            .select((reports::target_type, reports::target_id))
+            .for_update()
+            .first::<(String, i32)>(conn)?;
+        let target_type = target_type.as_str();
+        // Looked up first, as removing the target loses track of its author.
+        let author = match owner(conn, target_type, target_id) {
+            Err(Error::NotFound) => None,
+            result => Some(result?),
+        };
+
+        match action {
+            HIDE => set_hidden(conn, target_type, target_id, true)?,
+            REMOVE if target_type == ARTICLE => {
+                article::remove(conn, target_id, moderator)?;
+            }
+            REMOVE => {
+                comment::remove(conn, target_id, moderator)?;
+            }
+            SUSPEND => {
+                if let Some(author) = author {
+                    User::set_suspended(conn, author, true)?;
+                }
+            }
+            DISMISS => {
+                let hidden_by_moderator = diesel::select(diesel::dsl::exists(
+                    reports::table
+                        .filter(reports::target_type.eq(target_type))
+                        .filter(reports::target_id.eq(target_id))
+                        .filter(reports::action.eq(HIDE)),
+                ))
+                .get_result::<bool>(conn)?;
+                if !hidden_by_moderator {
+                    set_hidden(conn, target_type, target_id, false)?;
+                }
+            }
+            _ => {}
+        }
+        if let (Some(author), Some(message)) = (author, message) {
+            warning::create(conn, author, moderator, id, message)?;
+        }
+
+        let (status, action) = match action {
+            DISMISS => (DISMISSED, None),
+            action => (ACTIONED, Some(action)),
+        };
+        diesel::update(
+            reports::table.filter(
+                reports::id
+                    .eq(id)
+                    .or(reports::id.eq_any(open_on(target_type, target_id).select(reports::id))),
+            ),
+        )
+        .set((
+            reports::status.eq(status),
+            reports::action.eq(action),
+            reports::resolved_by.eq(moderator),
+            reports::resolved_at.eq(Utc::now()),
+        ))
+        .execute(conn)?;
+        get(conn, id)
+    })
+}
@@ -0,0 +1,180 @@
+use super::*;
+use crate::schema::*;
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, result::Error};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Revisions {
+    pub revisions: Vec<Revision>,
+    pub revisions_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Revision {
+    pub id: i32,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub changed_fields: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub author: Profile,
+    /// The revision this one restored, if it was made by a restore.
+    pub restored_from: Option<i32>,
+}
+
+impl Revision {
+    fn build(revision: RevisionData, profile: Profile) -> Self {
+        Revision {
+            id: revision.id,
+            title: revision.title,
+            description: revision.description,
+            body: revision.body,
+            changed_fields: revision.changed_fields,
+            created_at: revision.created_at,
+            author: profile,
+            restored_from: revision.restored_from,
+        }
+    }
+}
+
+#[derive(Queryable)]
+pub struct RevisionData {
+    pub id: i32,
+    pub article: i32,
+    pub author: i32,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub changed_fields: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub restored_from: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[table_name = "article_revisions"]
+pub struct RevisionForm<'a> {
+    pub article: i32,
+    pub author: i32,
+    pub title: &'a str,
+    pub description: &'a str,
+    pub body: &'a str,
+    pub changed_fields: Vec<&'a str>,
+    pub restored_from: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionDiff {
+    pub from: i32,
+    pub to: i32,
+    pub title: Vec<DiffLine>,
+    pub description: Vec<DiffLine>,
+    pub body: Vec<DiffLine>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub line: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+fn diff_lines(from: &str, to: &str) -> Vec<DiffLine> {
+    diff::lines(from, to)
+        .into_iter()
+        .map(|r| match r {
+            diff::Result::Left(l) => DiffLine {
+                op: DiffOp::Delete,
+                line: l.to_string(),
+            },
+            diff::Result::Both(l, _) => DiffLine {
+                op: DiffOp::Equal,
+                line: l.to_string(),
+            },
+            diff::Result::Right(r) => DiffLine {
+                op: DiffOp::Insert,
+                line: r.to_string(),
+            },
+        })
+        .collect()
+}
+
+pub fn record(conn: &PgConnection, revision: &RevisionForm) -> Result<usize, Error> {
+    diesel::insert_into(article_revisions::table)
+        .values(revision)
+        .execute(conn)
+}
+
+/// Revisions of the article at `slug`, most recent first.
+pub fn list(
+    conn: &PgConnection,
+    slug: &str,
+    page: &Page,
+    viewer: Option<i32>,
+) -> Result<Revisions, Error> {
+    let article_id = article::visible_article_id(conn, slug, viewer)?;
+    let rows = article_revisions::table
+        .inner_join(users::table)
+        .filter(article_revisions::article.eq(article_id))
+        .select((article_revisions::all_columns, users::all_columns))
+        .order(article_revisions::id.desc())
+        .limit(page.limit())
+        .offset(page.offset())
+        .load::<(RevisionData, User)>(conn)?;
+
+    let authors: Vec<i32> = rows.iter().map(|(r, _)| r.author).collect();
+    let viewer = Viewer::load(conn, viewer, &authors, &[])?;
+    let revisions: Vec<Revision> = rows
+        .into_iter()
+        .map(|(r, u)| {
+            let following = viewer.follows(u.id);
+            Revision::build(r, u.to_profile(following))
+        })
+        .collect();
+    let revisions_count = revisions.len() as i64;
+    Ok(Revisions {
+        revisions,
+        revisions_count,
+    })
+}
+
+pub fn find(
+    conn: &PgConnection,
+    slug: &str,
+    revision_id: i32,
+    viewer: Option<i32>,
+) -> Result<RevisionData, Error> {
+    let article_id = article::visible_article_id(conn, slug, viewer)?;
+    article_revisions::table
+        .filter(article_revisions::article.eq(article_id))
+        .filter(article_revisions::id.eq(revision_id))
+        .first::<RevisionData>(conn)
+}
+
+pub fn diff(
+    conn: &PgConnection,
+    slug: &str,
+    from: i32,
+    to: i32,
+    viewer: Option<i32>,
+) -> Result<RevisionDiff, Error> {
+    let old = find(conn, slug, from, viewer)?;
+    let new = find(conn, slug, to, viewer)?;
+    Ok(RevisionDiff {
+        from,
+        to,
+        title: diff_lines(&old.title, &new.title),
+        description: diff_lines(&old.description, &new.description),
+        body: diff_lines(&old.body, &new.body),
+    })
+}
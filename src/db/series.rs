@@ -0,0 +1,335 @@
+use super::*;
+use crate::schema::{articles, series, series_articles, users};
+use chrono::{DateTime, Utc};
+use diesel::{
+    prelude::*,
+    result::{DatabaseErrorKind, Error},
+    sql_types::Text,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesList {
+    pub series: Vec<Series>,
+    pub series_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Series {
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub owner: Profile,
+    pub articles: Vec<SeriesEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SeriesEntry {
+    pub slug: String,
+    pub title: String,
+    pub position: i32,
+}
+
+/// Where an article sits within its series.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SeriesInfo {
+    pub slug: String,
+    pub title: String,
+    pub position: i32,
+    pub previous: Option<String>,
+    pub next: Option<String>,
+}
+
+#[derive(Queryable)]
+pub struct SeriesData {
+    pub id: i32,
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub owner: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[table_name = "series"]
+pub struct SeriesForm {
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub owner: i32,
+}
+
+#[derive(AsChangeset, Default)]
+#[table_name = "series"]
+pub struct SeriesUpdateData {
+    pub slug: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+impl Series {
+    fn build(series: SeriesData, owner: Profile, articles: Vec<SeriesEntry>) -> Self {
+        Series {
+            slug: series.slug,
+            title: series.title,
+            description: series.description,
+            created_at: series.created_at,
+            updated_at: series.updated_at,
+            owner,
+            articles,
+        }
+    }
+}
+
+/// Builds a slug from `title` that no other series uses, appending `-2`,
+/// `-3`, ... on collision. `current` is the slug of the series being renamed.
+pub fn unique_slug(conn: &PgConnection, title: &str, current: Option<&str>) -> Result<String, Error> {
+    let mut base = slug::slugify(title);
+    if base.is_empty() {
+        base = "series".to_string();
+    }
+    let taken = series::table
+        .select(series::slug)
+        .filter(series::slug.eq(&base).or(series::slug.like(format!("{}-%", base))))
+        .filter(series::slug.ne(current.unwrap_or("")))
+        .load::<String>(conn)?;
+
+    let mut candidate = base.clone();
+    let mut n = 1;
+    while taken.contains(&candidate) {
+        n += 1;
+        candidate = format!("{}-{}", base, n);
+    }
+    Ok(candidate)
+}
+
+/// Runs `write` with the slug [`unique_slug`] picks for `title`, taking turns
+/// with writes of the same title and retrying on the next free slug when a
+/// concurrent write takes it anyway, like [`article::with_unique_slug`].
+pub fn with_unique_slug<T, F>(
+    conn: &PgConnection,
+    title: &str,
+    current: Option<&str>,
+    mut write: F,
+) -> Result<T, Error>
+where
+    F: FnMut(String) -> Result<T, Error>,
+{
+    let mut attempts = 1;
+    loop {
+        let written = conn.transaction(|| {
+            diesel::sql_query("SELECT pg_advisory_xact_lock('series'::regclass::int, hashtext($1))")
+                .bind::<Text, _>(slug::slugify(title))
+                .execute(conn)?;
+            write(unique_slug(conn, title, current)?)
+        });
+        match written {
+            Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, ref info))
+                if info.constraint_name() == Some("series_slug_key")
+                    && attempts < article::SLUG_ATTEMPTS =>
+            {
+                attempts += 1
+            }
+            result => return result,
+        }
+    }
+}
+
+fn entries(conn: &PgConnection, series_ids: &[i32]) -> Result<HashMap<i32, Vec<SeriesEntry>>, Error> {
+    let mut entries: HashMap<i32, Vec<SeriesEntry>> = HashMap::new();
+    if series_ids.is_empty() {
+        return Ok(entries);
+    }
+    let rows = series_articles::table
+        .inner_join(articles::table)
+        .filter(series_articles::series.eq_any(series_ids))
+        .filter(articles::hidden_at.is_null())
+        .filter(articles::deleted_at.is_null())
+        .select((
+            series_articles::series,
+            articles::slug,
+            articles::title,
+            series_articles::position,
+        ))
+        .order(series_articles::position)
+        .load::<(i32, String, String, i32)>(conn)?;
+    for (series, slug, title, position) in rows {
+        entries.entry(series).or_default().push(SeriesEntry {
+            slug,
+            title,
+            position,
+        });
+    }
+    Ok(entries)
+}
+
+fn build_all(
+    conn: &PgConnection,
+    rows: Vec<(SeriesData, User)>,
+    viewer: Option<i32>,
+) -> Result<Vec<Series>, Error> {
+    let ids: Vec<i32> = rows.iter().map(|(s, _)| s.id).collect();
+    let owners: Vec<i32> = rows.iter().map(|(s, _)| s.owner).collect();
+    let mut entries = entries(conn, &ids)?;
+    let viewer = Viewer::load(conn, viewer, &owners, &[])?;
+    Ok(rows
+        .into_iter()
+        .map(|(series, owner)| {
+            let profile = owner.to_profile(viewer.follows(owner.id));
+            let articles = entries.remove(&series.id).unwrap_or_default();
+            Series::build(series, profile, articles)
+        })
+        .collect())
+}
+
+/// Replaces the articles of a series with `slugs` in that order. Every
+/// article must have been written by `owner`.
+fn set_articles(conn: &PgConnection, series_id: i32, owner: i32, slugs: &[String]) -> Result<(), Error> {
+    let ids: HashMap<String, i32> = articles::table
+        .filter(articles::slug.eq_any(slugs))
+        .filter(articles::author.eq(owner))
+        .filter(articles::deleted_at.is_null())
+        .select((articles::slug, articles::id))
+        .load::<(String, i32)>(conn)?
+        .into_iter()
+        .collect();
+
+    let mut rows = Vec::with_capacity(slugs.len());
+    for (i, slug) in slugs.iter().enumerate() {
+        let article = *ids.get(slug).ok_or(Error::NotFound)?;
+        rows.push((
+            series_articles::series.eq(series_id),
+            series_articles::article.eq(article),
+            series_articles::position.eq(i as i32 + 1),
+        ));
+    }
+
+    diesel::delete(series_articles::table.filter(series_articles::series.eq(series_id)))
+        .execute(conn)?;
+    diesel::insert_into(series_articles::table)
+        .values(&rows)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn create(conn: &PgConnection, form: &SeriesForm, articles: &[String]) -> Result<Series, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let series = diesel::insert_into(series::table)
+            .values(form)
+            .get_result::<SeriesData>(conn)?;
+        set_articles(conn, series.id, form.owner, articles)?;
+        get(conn, &series.slug, Some(form.owner))
+    })
+}
+
+pub fn get(conn: &PgConnection, slug: &str, viewer: Option<i32>) -> Result<Series, Error> {
+    let row = series::table
+        .inner_join(users::table)
+        .filter(series::slug.eq(slug))
+        .select((series::all_columns, users::all_columns))
+        .first::<(SeriesData, User)>(conn)?;
+    Ok(build_all(conn, vec![row], viewer)?.remove(0))
+}
+
+/// Series owned by `owner`, or all series, most recent first.
+pub fn list(
+    conn: &PgConnection,
+    owner: Option<&str>,
+    page: &Page,
+    viewer: Option<i32>,
+) -> Result<SeriesList, Error> {
+    let mut query = series::table
+        .inner_join(users::table)
+        .select((series::all_columns, users::all_columns))
+        .into_boxed();
+    if let Some(owner) = owner {
+        query = query.filter(users::username.eq(owner));
+    }
+    let rows = query
+        .order(series::created_at.desc())
+        .limit(page.limit())
+        .offset(page.offset())
+        .load::<(SeriesData, User)>(conn)?;
+
+    let series = build_all(conn, rows, viewer)?;
+    let series_count = series.len() as i64;
+    Ok(SeriesList {
+        series,
+        series_count,
+    })
+}
+
+pub fn update(
+    conn: &PgConnection,
+    slug: &str,
+    owner: i32,
+    data: &SeriesUpdateData,
+    articles: Option<&[String]>,
+) -> Result<Series, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let target = series::table.filter(series::slug.eq(slug).and(series::owner.eq(owner)));
+        let series = if data.slug.is_some() || data.title.is_some() || data.description.is_some() {
+            diesel::update(target).set(data).get_result::<SeriesData>(conn)?
+        } else {
+            target.for_update().first::<SeriesData>(conn)?
+        };
+        if let Some(articles) = articles {
+            set_articles(conn, series.id, owner, articles)?;
+        }
+        get(conn, &series.slug, Some(owner))
+    })
+}
+
+pub fn delete(conn: &PgConnection, slug: &str, owner: i32) -> Result<usize, Error> {
+    diesel::delete(series::table.filter(series::slug.eq(slug).and(series::owner.eq(owner))))
+        .execute(conn)
+}
+
+/// Series placement of each of `articles` that belongs to a series.
+pub fn info(conn: &PgConnection, articles: &[i32]) -> Result<HashMap<i32, SeriesInfo>, Error> {
+    let mut info = HashMap::new();
+    if articles.is_empty() {
+        return Ok(info);
+    }
+    let memberships = series_articles::table
+        .inner_join(series::table)
+        .filter(series_articles::article.eq_any(articles))
+        .select((
+            series_articles::article,
+            series::id,
+            series::slug,
+            series::title,
+            series_articles::position,
+        ))
+        .load::<(i32, i32, String, String, i32)>(conn)?;
+
+    let ids: Vec<i32> = memberships.iter().map(|m| m.1).collect();
+    let entries = entries(conn, &ids)?;
+    for (article, series, slug, title, position) in memberships {
+        let siblings = entries.get(&series).map(Vec::as_slice).unwrap_or(&[]);
+        let at = siblings.iter().position(|e| e.position == position);
+        let previous = at
+            .and_then(|i| i.checked_sub(1))
+            .map(|i| siblings[i].slug.clone());
+        let next = at
+            .and_then(|i| siblings.get(i + 1))
+            .map(|e| e.slug.clone());
+        info.insert(
+            article,
+            SeriesInfo {
+                slug,
+                title,
+                position,
+                previous,
+                next,
+            },
+        );
+    }
+    Ok(info)
+}
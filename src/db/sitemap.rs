@@ -0,0 +1,255 @@
+use crate::schema::*;
+use chrono::{DateTime, Utc};
+use diesel::{
+    dsl::{exists, not},
+    prelude::*,
+    result::Error,
+    sql_types::{BigInt, Timestamptz},
+};
+
+/// The most URLs a single sitemap may list.
+pub const PAGE_SIZE: i64 = 50_000;
+
+pub const ARTICLE: &str = "article";
+pub const PROFILE: &str = "profile";
+pub const TAG: &str = "tag";
+
+#[derive(Queryable, Debug)]
+pub struct Entry {
+    pub kind: String,
+    pub name: String,
+    pub lastmod: DateTime<Utc>,
+}
+
+#[derive(QueryableByName, Debug)]
+pub struct Page {
+    #[sql_type = "BigInt"]
+    pub page: i64,
+    #[sql_type = "Timestamptz"]
+    pub lastmod: DateTime<Utc>,
+}
+
+fn upsert(
+    conn: &PgConnection,
+    kind: &str,
+    item: i32,
+    name: &str,
+    lastmod: DateTime<Utc>,
+) -> Result<(), Error> {
+    // Updating first keeps the id, and with it the page, of known entries;
+    // an upsert would use up a sequence value on every call.
+    let updated = diesel::update(
+        sitemap_entries::table.filter(
+            sitemap_entries::kind
+                .eq(kind)
+                .and(sitemap_entries::item.eq(item)),
+        ),
+    )
+    .set((
+        sitemap_entries::name.eq(name),
+        sitemap_entries::lastmod.eq(lastmod),
+    ))
+    .execute(conn)?;
+    if updated == 0 {
+        diesel::insert_into(sitemap_entries::table)
+            .values((
+                sitemap_entries::kind.eq(kind),
+                sitemap_entries::item.eq(item),
+                sitemap_entries::name.eq(name),
+                sitemap_entries::lastmod.eq(lastmod),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Lists, or refreshes, an article along with the pages of its author,
+/// accepted co-authors and tags. Hidden and deleted articles are unlisted
+/// instead.
+pub fn article(conn: &PgConnection, article_id: i32) -> Result<(), Error> {
+    let (slug, author, username, updated_at, tag_list, hidden_at, deleted_at) = articles::table
+        .inner_join(users::table)
+        .filter(articles::id.eq(article_id))
+        .select((
+            articles::slug,
+            articles::author,
+            users::username,
+            articles::updated_at,
+            articles::tag_list,
+            articles::hidden_at,
+            articles::deleted_at,
+        ))
+        .first::<(
+            String,
+            i32,
+            String,
+            DateTime<Utc>,
+            Vec<String>,
+            Option<DateTime<Utc>>,
+            Option<DateTime<Utc>>,
+        )>(conn)?;
+    if hidden_at.is_some() || deleted_at.is_some() {
+        return remove_article(conn, article_id, author, &tag_list);
+    }
+    upsert(conn, ARTICLE, article_id, &slug, updated_at)?;
+    upsert(conn, PROFILE, author, &username, updated_at)?;
+    for (id, username) in coauthors(conn, article_id)? {
+        upsert(conn, PROFILE, id, &username, updated_at)?;
+    }
+
+    let tags = article_tags::table
+        .inner_join(tags::table)
+        .filter(article_tags::article.eq(article_id))
+        .select((tags::id, tags::name))
+        .load::<(i32, String)>(conn)?;
+    for (id, name) in tags {
+        upsert(conn, TAG, id, &name, updated_at)?;
+    }
+    Ok(())
+}
+
+fn coauthors(conn: &PgConnection, article_id: i32) -> Result<Vec<(i32, String)>, Error> {
+    article_authors::table
+        .inner_join(users::table)
+        .filter(article_authors::article.eq(article_id))
+        .filter(article_authors::accepted_at.is_not_null())
+        .select((users::id, users::username))
+        .load(conn)
+}
+
+/// Drops the pages of `author` and of `tags` that no longer have articles.
+/// Articles someone co-authors count towards their page too.
+pub fn prune(conn: &PgConnection, author: i32, tags: &[String]) -> Result<(), Error> {
+    diesel::delete(
+        sitemap_entries::table
+            .filter(sitemap_entries::kind.eq(TAG))
+            .filter(sitemap_entries::name.eq_any(tags))
+            .filter(not(exists(
+                article_tags::table
+                    .inner_join(articles::table)
+                    .filter(article_tags::tag.eq(sitemap_entries::item))
+                    .filter(articles::hidden_at.is_null())
+                    .filter(articles::deleted_at.is_null()),
+            ))),
+    )
+    .execute(conn)?;
+    diesel::delete(
+        sitemap_entries::table
+            .filter(sitemap_entries::kind.eq(PROFILE))
+            .filter(sitemap_entries::item.eq(author))
+            .filter(not(exists(
+                articles::table
+                    .filter(articles::author.eq(sitemap_entries::item))
+                    .filter(articles::hidden_at.is_null())
+                    .filter(articles::deleted_at.is_null()),
+            )))
+            .filter(not(exists(
+                article_authors::table
+                    .inner_join(articles::table)
+                    .filter(article_authors::user.eq(sitemap_entries::item))
+                    .filter(article_authors::accepted_at.is_not_null())
+                    .filter(articles::hidden_at.is_null())
+                    .filter(articles::deleted_at.is_null()),
+            ))),
+    )
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Unlists a deleted article, and its authors and tags if it was their last.
+pub fn remove_article(
+    conn: &PgConnection,
+    article_id: i32,
+    author: i32,
+    tags: &[String],
+) -> Result<(), Error> {
+    diesel::delete(
+        sitemap_entries::table.filter(
+            sitemap_entries::kind
+                .eq(ARTICLE)
+                .and(sitemap_entries::item.eq(article_id)),
+        ),
+    )
+    .execute(conn)?;
+    prune(conn, author, tags)?;
+    for (id, _) in coauthors(conn, article_id)? {
+        prune(conn, id, &[])?;
+    }
+    Ok(())
+}
+
+/// Follows a change of username.
+pub fn rename_profile(conn: &PgConnection, user_id: i32, username: &str) -> Result<usize, Error> {
+    diesel::update(
+        sitemap_entries::table.filter(
+            sitemap_entries::kind
+                .eq(PROFILE)
+                .and(sitemap_entries::item.eq(user_id)),
+        ),
+    )
+    .set(sitemap_entries::name.eq(username))
+    .execute(conn)
+}
+
+/// Each page of the sitemap with its most recent change.
+pub fn pages(conn: &PgConnection) -> Result<Vec<Page>, Error> {
+    diesel::sql_query(
+        "SELECT ((id - 1) / $1)::bigint AS page, max(lastmod) AS lastmod \
+         FROM sitemap_entries GROUP BY 1 ORDER BY 1",
+    )
+    .bind::<BigInt, _>(PAGE_SIZE)
+    .load::<Page>(conn)
+}
+
+pub fn entries(conn: &PgConnection, page: i64) -> Result<Vec<Entry>, Error> {
+    let first = (page * PAGE_SIZE + 1) as i32;
+    let last = ((page + 1) * PAGE_SIZE) as i32;
+    sitemap_entries::table
+        .filter(sitemap_entries::id.between(first, last))
+        .order(sitemap_entries::id)
+        .select((
+            sitemap_entries::kind,
+            sitemap_entries::name,
+            sitemap_entries::lastmod,
+        ))
+        .load::<Entry>(conn)
+}
+
+/// Rebuilds every entry from scratch, closing the gaps deleted entries
+/// leave in the pages.
+pub fn rebuild(conn: &PgConnection) -> Result<usize, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        diesel::sql_query("TRUNCATE sitemap_entries RESTART IDENTITY").execute(conn)?;
+        let mut count = diesel::sql_query(
+            "INSERT INTO sitemap_entries (kind, item, name, lastmod) \
+             SELECT 'article', id, slug, updated_at FROM articles \
+             WHERE hidden_at IS NULL AND deleted_at IS NULL ORDER BY id",
+        )
+        .execute(conn)?;
+        count += diesel::sql_query(
+            "INSERT INTO sitemap_entries (kind, item, name, lastmod) \
+             SELECT 'profile', users.id, users.username, max(articles.updated_at) \
+             FROM users \
+             INNER JOIN (SELECT id, author AS user_id FROM articles \
+                         UNION SELECT article, \"user\" FROM article_authors \
+                         WHERE accepted_at IS NOT NULL) AS authored \
+                     ON authored.user_id = users.id \
+             INNER JOIN articles ON articles.id = authored.id \
+             WHERE articles.hidden_at IS NULL AND articles.deleted_at IS NULL \
+             GROUP BY users.id ORDER BY users.id",
+        )
+        .execute(conn)?;
+        count += diesel::sql_query(
+            "INSERT INTO sitemap_entries (kind, item, name, lastmod) \
+             SELECT 'tag', tags.id, tags.name, max(articles.updated_at) \
+             FROM tags \
+             INNER JOIN article_tags ON article_tags.tag = tags.id \
+             INNER JOIN articles ON articles.id = article_tags.article \
+             WHERE articles.hidden_at IS NULL AND articles.deleted_at IS NULL \
+             GROUP BY tags.id ORDER BY tags.id",
+        )
+        .execute(conn)?;
+        Ok(count)
+    })
+}
@@ -0,0 +1,189 @@
+use crate::{schema::*, views::View};
+use chrono::{DateTime, Duration, Utc};
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+    result::Error,
+    sql_types::{BigInt, Date, Int4, Text, Timestamptz},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    pub bucket: String,
+    pub since: DateTime<Utc>,
+    pub articles: Vec<ArticleStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticleStats {
+    pub slug: String,
+    pub title: String,
+    pub favorites_count: i32,
+    pub views: i64,
+    pub comments: i64,
+    pub buckets: Vec<Bucket>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Bucket {
+    pub start: DateTime<Utc>,
+    pub views: i64,
+    pub comments: i64,
+    pub favorites: i64,
+}
+
+impl Bucket {
+    fn new(start: DateTime<Utc>) -> Self {
+        Bucket {
+            start,
+            views: 0,
+            comments: 0,
+            favorites: 0,
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct BucketRow {
+    #[sql_type = "Text"]
+    slug: String,
+    #[sql_type = "Timestamptz"]
+    bucket: DateTime<Utc>,
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
+/// Stores buffered views, ignoring those already recorded for the same
+/// visitor and day.
+pub fn record_views(conn: &PgConnection, views: &[View]) -> Result<usize, Error> {
+    let ids: Vec<i32> = views.iter().map(|v| v.article).collect();
+    let live: Vec<i32> = articles::table
+        .filter(articles::id.eq_any(ids))
+        .filter(articles::deleted_at.is_null())
+        .select(articles::id)
+        .load::<i32>(conn)?;
+
+    let rows: Vec<_> = views
+        .iter()
+        .filter(|v| live.contains(&v.article))
+        .map(|v| {
+            (
+                article_views::article.eq(v.article),
+                article_views::visitor.eq(&v.visitor),
+                article_views::viewed_on.eq(v.viewed_on),
+            )
+        })
+        .collect();
+    diesel::insert_into(article_views::table)
+        .values(&rows)
+        .on_conflict_do_nothing()
+        .execute(conn)
+}
+
+/// Views, comments and favorites of every article written by `author` over
+/// the last `days` days, grouped into `bucket`s (`day`, `week` or `month`).
+pub fn author_stats(
+    conn: &PgConnection,
+    author: i32,
+    bucket: &str,
+    days: i64,
+) -> Result<Stats, Error> {
+    let since = Utc::now() - Duration::days(days);
+
+    let views = diesel::sql_query(
+        "SELECT articles.slug, date_trunc($1, article_views.viewed_on::timestamptz) AS bucket, \
+                count(*) AS count \
+         FROM article_views INNER JOIN articles ON articles.id = article_views.article \
+         WHERE articles.author = $2 AND article_views.viewed_on >= $3 \
+           AND articles.deleted_at IS NULL \
+         GROUP BY 1, 2",
+    )
+    .bind::<Text, _>(bucket)
+    .bind::<Int4, _>(author)
+    .bind::<Date, _>(since.naive_utc().date())
+    .load::<BucketRow>(conn)?;
+
+    let comments = diesel::sql_query(
+        "SELECT articles.slug, date_trunc($1, comments.created_at) AS bucket, count(*) AS count \
+         FROM comments INNER JOIN articles ON articles.id = comments.article \
+         WHERE articles.author = $2 AND comments.created_at >= $3 \
+           AND articles.deleted_at IS NULL AND comments.deleted_at IS NULL \
+         GROUP BY 1, 2",
+    )
+    .bind::<Text, _>(bucket)
+    .bind::<Int4, _>(author)
+    .bind::<Timestamptz, _>(since)
+    .load::<BucketRow>(conn)?;
+
+    let favorites = diesel::sql_query(
+        "SELECT articles.slug, date_trunc($1, favorites.created_at) AS bucket, count(*) AS count \
+         FROM favorites INNER JOIN articles ON articles.id = favorites.article \
+         WHERE articles.author = $2 AND favorites.created_at >= $3 \
+           AND articles.deleted_at IS NULL \
+         GROUP BY 1, 2",
+    )
+    .bind::<Text, _>(bucket)
+    .bind::<Int4, _>(author)
+    .bind::<Timestamptz, _>(since)
+    .load::<BucketRow>(conn)?;
+
+    let mut buckets: BTreeMap<String, BTreeMap<DateTime<Utc>, Bucket>> = BTreeMap::new();
+    for row in views {
+        buckets
+            .entry(row.slug)
+            .or_default()
+            .entry(row.bucket)
+            .or_insert(Bucket::new(row.bucket))
+            .views = row.count;
+    }
+    for row in comments {
+        buckets
+            .entry(row.slug)
+            .or_default()
+            .entry(row.bucket)
+            .or_insert(Bucket::new(row.bucket))
+            .comments = row.count;
+    }
+    for row in favorites {
+        buckets
+            .entry(row.slug)
+            .or_default()
+            .entry(row.bucket)
+            .or_insert(Bucket::new(row.bucket))
+            .favorites = row.count;
+    }
+
+    let articles = articles::table
+        .filter(articles::author.eq(author))
+        .filter(articles::deleted_at.is_null())
+        .select((articles::slug, articles::title, articles::favorites_count))
+        .order(articles::created_at.desc())
+        .load::<(String, String, i32)>(conn)?
+        .into_iter()
+        .map(|(slug, title, favorites_count)| {
+            let buckets: Vec<Bucket> = buckets
+                .remove(&slug)
+                .map(|b| b.into_values().collect())
+                .unwrap_or_default();
+            ArticleStats {
+                views: buckets.iter().map(|b| b.views).sum(),
+                comments: buckets.iter().map(|b| b.comments).sum(),
+                slug,
+                title,
+                favorites_count,
+                buckets,
+            }
+        })
+        .collect();
+
+    Ok(Stats {
+        bucket: bucket.to_owned(),
+        since,
+        articles,
+    })
+}
@@ -0,0 +1,136 @@
+use crate::schema::*;
+use chrono::{Duration, Utc};
+use diesel::{dsl::sql, pg::PgConnection, prelude::*, result::Error, sql_types::BigInt};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Queryable, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    pub name: String,
+    pub description: Option<String>,
+    pub articles_count: i64,
+}
+
+fn articles_count() -> diesel::expression::SqlLiteral<BigInt> {
+    sql::<BigInt>("count(article_tags.article)")
+}
+
+/// Normalized form of a tag name as stored in `tags` and `articles.tag_list`.
+pub fn normalize(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Makes the `article_tags` rows of an article match `names`, creating
+/// missing tags on the way.
+pub fn sync(conn: &PgConnection, article_id: i32, names: &[String]) -> Result<(), Error> {
+    let new_tags: Vec<_> = names.iter().map(|name| tags::name.eq(name)).collect();
+    diesel::insert_into(tags::table)
+        .values(&new_tags)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    let tag_ids = tags::table
+        .filter(tags::name.eq_any(names))
+        .select(tags::id)
+        .load::<i32>(conn)?;
+
+    diesel::delete(
+        article_tags::table.filter(
+            article_tags::article
+                .eq(article_id)
+                .and(diesel::dsl::not(article_tags::tag.eq_any(&tag_ids))),
+        ),
+    )
+    .execute(conn)?;
+
+    let links: Vec<_> = tag_ids
+        .iter()
+        .map(|id| (article_tags::article.eq(article_id), article_tags::tag.eq(id)))
+        .collect();
+    diesel::insert_into(article_tags::table)
+        .values(&links)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Every tag in use with its number of articles, most used first.
+pub fn popular(conn: &PgConnection) -> Result<Vec<(String, i64)>, Error> {
+    tags::table
+        .inner_join(article_tags::table)
+        .group_by(tags::id)
+        .select((tags::name, articles_count()))
+        .order((articles_count().desc(), tags::name))
+        .load::<(String, i64)>(conn)
+}
+
+/// Tags attached to the most articles during the last `days` days.
+pub fn trending(conn: &PgConnection, days: i64, limit: i64) -> Result<Vec<Tag>, Error> {
+    let since = Utc::now() - Duration::days(days);
+    tags::table
+        .inner_join(article_tags::table)
+        .filter(article_tags::created_at.ge(since))
+        .group_by(tags::id)
+        .select((tags::name, tags::description, articles_count()))
+        .order((articles_count().desc(), tags::name))
+        .limit(limit)
+        .load::<Tag>(conn)
+}
+
+pub fn find(conn: &PgConnection, name: &str) -> Result<Tag, Error> {
+    tags::table
+        .left_join(article_tags::table)
+        .filter(tags::name.eq(normalize(name)))
+        .group_by(tags::id)
+        .select((tags::name, tags::description, articles_count()))
+        .first::<Tag>(conn)
+}
+
+/// Sets or, with `None`, clears the description of an existing tag.
+pub fn describe(conn: &PgConnection, name: &str, description: Option<&str>) -> Result<Tag, Error> {
+    let name = normalize(name);
+    let updated = diesel::update(tags::table.filter(tags::name.eq(&name)))
+        .set(tags::description.eq(description))
+        .execute(conn)?;
+    if updated == 0 {
+        return Err(Error::NotFound);
+    }
+    find(conn, &name)
+}
+
+pub fn follow(conn: &PgConnection, name: &str, follower: i32) -> Result<Tag, Error> {
+    let name = normalize(name);
+    diesel::insert_into(tags::table)
+        .values(tags::name.eq(&name))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    let tag_id = tags::table
+        .filter(tags::name.eq(&name))
+        .select(tags::id)
+        .first::<i32>(conn)?;
+    diesel::insert_into(tag_follows::table)
+        .values((tag_follows::follower.eq(follower), tag_follows::tag.eq(tag_id)))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    find(conn, &name)
+}
+
+pub fn unfollow(conn: &PgConnection, name: &str, follower: i32) -> Result<Tag, Error> {
+    let name = normalize(name);
+    let tag_id = tags::table
+        .filter(tags::name.eq(&name))
+        .select(tags::id)
+        .first::<i32>(conn)?;
+    diesel::delete(tag_follows::table.find((follower, tag_id))).execute(conn)?;
+    find(conn, &name)
+}
+
+/// Names of the tags `follower` follows.
+pub fn followed(conn: &PgConnection, follower: i32) -> Result<Vec<String>, Error> {
+    tags::table
+        .inner_join(tag_follows::table)
+        .filter(tag_follows::follower.eq(follower))
+        .select(tags::name)
+        .order(tags::name)
+        .load::<String>(conn)
+}
@@ -0,0 +1,229 @@
+use super::*;
+use crate::schema::*;
+use chrono::{DateTime, Duration, Utc};
+use diesel::{
+    prelude::*,
+    result::Error,
+    sql_types::{Array, BigInt, Integer, Text, Timestamptz},
+};
+use serde::{Deserialize, Serialize};
+
+/// Uploads and stored files younger than this are never collected, so a
+/// file can be uploaded before the profile or article that uses it is saved.
+pub const ORPHAN_GRACE_HOURS: i64 = 24;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Uploads {
+    pub uploads: Vec<Upload>,
+    pub uploads_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Upload {
+    pub hash: String,
+    pub url: String,
+    pub content_type: String,
+    pub size: i64,
+    pub filename: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, QueryableByName)]
+#[table_name = "uploads"]
+pub struct UploadData {
+    pub id: i32,
+    pub owner: i32,
+    pub hash: String,
+    pub content_type: String,
+    pub size: i64,
+    pub filename: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Upload {
+    fn build(upload: UploadData) -> Self {
+        Upload {
+            url: url(&upload.hash),
+            hash: upload.hash,
+            content_type: upload.content_type,
+            size: upload.size,
+            filename: upload.filename,
+            created_at: upload.created_at,
+        }
+    }
+}
+
+/// Where uploads are served from; an upload's URL is this and its hash.
+pub const URL_PREFIX: &str = "/api/uploads/";
+
+pub fn url(hash: &str) -> String {
+    format!("{}{}", URL_PREFIX, hash)
+}
+
+/// Bytes stored by `owner`, counting each distinct file once.
+pub fn used(conn: &PgConnection, owner: i32) -> Result<i64, Error> {
+    uploads::table
+        .filter(uploads::owner.eq(owner))
+        .select(diesel::dsl::sql::<BigInt>("coalesce(sum(size), 0)::bigint"))
+        .first::<i64>(conn)
+}
+
+/// Locks the row of `owner` until the end of the transaction, so that their
+/// concurrent uploads are checked against the quota one at a time.
+pub fn lock_owner(conn: &PgConnection, owner: i32) -> Result<(), Error> {
+    users::table
+        .find(owner)
+        .select(users::id)
+        .for_update()
+        .first::<i32>(conn)
+        .map(|_| ())
+}
+
+/// Whether storing `files`, as hash and size, keeps `owner` within `quota`.
+/// Files they already uploaded take up no more space.
+pub fn fits(
+    conn: &PgConnection,
+    owner: i32,
+    files: &[(String, i64)],
+    quota: i64,
+) -> Result<bool, Error> {
+    let hashes: Vec<&str> = files.iter().map(|(hash, _)| hash.as_str()).collect();
+    let uploaded: Vec<String> = uploads::table
+        .filter(uploads::owner.eq(owner).and(uploads::hash.eq_any(hashes)))
+        .select(uploads::hash)
+        .load(conn)?;
+    let added: i64 = files
+        .iter()
+        .filter(|(hash, _)| !uploaded.contains(hash))
+        .map(|(_, size)| size)
+        .sum();
+    Ok(added == 0 || used(conn, owner)? + added <= quota)
+}
+
+/// Records that `owner` uploaded the blob `hash`. Uploading the same file
+/// again returns the existing record.
+pub fn create(
+    conn: &PgConnection,
+    owner: i32,
+    hash: &str,
+    content_type: &str,
+    size: i64,
+    filename: Option<&str>,
+) -> Result<Upload, Error> {
+    diesel::insert_into(uploads::table)
+        .values((
+            uploads::owner.eq(owner),
+            uploads::hash.eq(hash),
+            uploads::content_type.eq(content_type),
+            uploads::size.eq(size),
+            uploads::filename.eq(filename),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    uploads::table
+        .filter(uploads::owner.eq(owner).and(uploads::hash.eq(hash)))
+        .first::<UploadData>(conn)
+        .map(Upload::build)
+}
+
+/// The content type `hash` was uploaded with.
+pub fn content_type(conn: &PgConnection, hash: &str) -> Result<String, Error> {
+    uploads::table
+        .filter(uploads::hash.eq(hash))
+        .select(uploads::content_type)
+        .first::<String>(conn)
+}
+
+pub fn list(conn: &PgConnection, owner: i32, page: &Page) -> Result<Uploads, Error> {
+    let uploads: Vec<Upload> = uploads::table
+        .filter(uploads::owner.eq(owner))
+        .order(uploads::created_at.desc())
+        .limit(page.limit())
+        .offset(page.offset())
+        .load::<UploadData>(conn)?
+        .into_iter()
+        .map(Upload::build)
+        .collect();
+    let uploads_count = uploads.len() as i64;
+    Ok(Uploads {
+        uploads,
+        uploads_count,
+    })
+}
+
+/// Whether `owner` uploaded the blob `hash`.
+pub fn is_owned(conn: &PgConnection, owner: i32, hash: &str) -> Result<bool, Error> {
+    diesel::select(diesel::dsl::exists(
+        uploads::table.filter(uploads::owner.eq(owner).and(uploads::hash.eq(hash))),
+    ))
+    .get_result::<bool>(conn)
+}
+
+/// Whether any user still holds the blob `hash`.
+pub fn is_stored(conn: &PgConnection, hash: &str) -> Result<bool, Error> {
+    diesel::select(diesel::dsl::exists(
+        uploads::table.filter(uploads::hash.eq(hash)),
+    ))
+    .get_result::<bool>(conn)
+}
+
+/// Matches uploads `u` that no profile image, avatar, article body or cover
+/// image links to, counting the bodies of earlier revisions so that
+/// restoring one brings its images back too.
+const UNUSED: &str = "NOT EXISTS (SELECT 1 FROM users WHERE users.image LIKE '%' || u.hash || '%') \
+     AND NOT EXISTS (SELECT 1 FROM users WHERE users.avatar::text LIKE '%' || u.hash || '%') \
+     AND NOT EXISTS (SELECT 1 FROM articles WHERE articles.body LIKE '%' || u.hash || '%') \
+     AND NOT EXISTS (SELECT 1 FROM articles WHERE articles.cover_image LIKE '%' || u.hash || '%') \
+     AND NOT EXISTS (SELECT 1 FROM article_revisions WHERE article_revisions.body LIKE '%' || u.hash || '%')";
+
+/// Removes the upload of `hash` by `owner` unless something still links to
+/// it.
+pub fn delete(conn: &PgConnection, owner: i32, hash: &str) -> Result<usize, Error> {
+    diesel::sql_query(format!(
+        "DELETE FROM uploads u WHERE u.owner = $1 AND u.hash = $2 AND {}",
+        UNUSED
+    ))
+    .bind::<Integer, _>(owner)
+    .bind::<Text, _>(hash)
+    .execute(conn)
+}
+
+/// Deletes uploads past the grace period that nothing links to, returning
+/// them.
+pub fn delete_orphans(conn: &PgConnection) -> Result<Vec<UploadData>, Error> {
+    diesel::sql_query(format!(
+        "DELETE FROM uploads u WHERE u.created_at < $1 AND {} RETURNING u.*",
+        UNUSED
+    ))
+    .bind::<Timestamptz, _>(Utc::now() - Duration::hours(ORPHAN_GRACE_HOURS))
+    .load::<UploadData>(conn)
+}
+
+/// Removes the uploads of `hashes` by `owner` that nothing links to any
+/// more, returning the hashes no user holds after that.
+pub fn release(conn: &PgConnection, owner: i32, hashes: &[String]) -> Result<Vec<String>, Error> {
+    let released = diesel::sql_query(format!(
+        "DELETE FROM uploads u WHERE u.owner = $1 AND u.hash = ANY($2) AND {} RETURNING u.*",
+        UNUSED
+    ))
+    .bind::<Integer, _>(owner)
+    .bind::<Array<Text>, _>(hashes)
+    .load::<UploadData>(conn)?;
+    let mut unheld = Vec::new();
+    for upload in released {
+        if !is_stored(conn, &upload.hash)? {
+            unheld.push(upload.hash);
+        }
+    }
+    Ok(unheld)
+}
+
+/// Every blob hash that is still uploaded by someone.
+pub fn hashes(conn: &PgConnection) -> Result<Vec<String>, Error> {
+    uploads::table
+        .select(uploads::hash)
+        .distinct()
+        .load::<String>(conn)
+}
@@ -1,14 +1,28 @@
 extern crate jsonwebtoken as jwt;
-use super::{Crud, Profile};
+use super::{sitemap, upload, Crud, Profile};
 
 use crate::{
     auth::{Claims, Jwt},
+    avatar::{self, Format, SIZES},
     schema::users,
 };
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use diesel::{deserialize::Queryable, pg::Pg, prelude::*, result::Error};
 use jwt::{encode, Header};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// URLs (or, as stored in `users.avatar`, upload hashes) of an avatar by
+/// size and then by format.
+pub type Avatar = BTreeMap<u32, BTreeMap<String, String>>;
+
+pub const USER: &str = "user";
+/// May work through the moderation queue.
+pub const MODERATOR: &str = "moderator";
+/// May bulk import and export articles, and moderate.
+pub const ADMIN: &str = "admin";
+pub const ROLES: [&str; 3] = [USER, MODERATOR, ADMIN];
 
 #[derive(Debug, Serialize)]
 pub struct User {
@@ -20,11 +34,30 @@ pub struct User {
     pub image: Option<String>,
     #[serde(skip_serializing)]
     pub password: String,
+    #[serde(skip_serializing)]
+    pub avatar: Option<Value>,
+    #[serde(skip_serializing)]
+    pub role: String,
+    #[serde(skip_serializing)]
+    pub suspended_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub created_at: DateTime<Utc>,
     pub token: String,
 }
 
 impl Queryable<users::SqlType, Pg> for User {
-    type Row = (i32, String, String, Option<String>, Option<String>, String);
+    type Row = (
+        i32,
+        String,
+        String,
+        Option<String>,
+        Option<String>,
+        String,
+        Option<Value>,
+        String,
+        Option<DateTime<Utc>>,
+        DateTime<Utc>,
+    );
 
     fn build(row: Self::Row) -> Self {
         User {
@@ -34,6 +67,10 @@ impl Queryable<users::SqlType, Pg> for User {
             bio: row.3,
             image: row.4,
             password: row.5,
+            avatar: row.6,
+            role: row.7,
+            suspended_at: row.8,
+            created_at: row.9,
             token: "".to_string(),
         }
     }
@@ -61,9 +98,13 @@ impl Crud<UserForm> for User {
     }
 
     fn update(conn: &PgConnection, user_id: i32, form: &UserForm) -> Result<Self, Error> {
-        diesel::update(users::table.find(user_id))
-            .set(form)
-            .get_result::<User>(conn)
+        conn.transaction::<_, Error, _>(|| {
+            let user = diesel::update(users::table.find(user_id))
+                .set(form)
+                .get_result::<User>(conn)?;
+            sitemap::rename_profile(conn, user.id, &user.username)?;
+            Ok(user)
+        })
     }
 
     fn delete(conn: &PgConnection, user_id: i32) -> Result<usize, Error> {
@@ -71,6 +112,14 @@ impl Crud<UserForm> for User {
     }
 }
 
+fn largest_png(hashes: &Avatar) -> Option<String> {
+    hashes
+        .values()
+        .last()
+        .and_then(|formats| formats.get(Format::Png.name()))
+        .map(|hash| upload::url(hash))
+}
+
 impl User {
     pub fn with_email(conn: &PgConnection, email: &str) -> Result<Self, Error> {
         users::table
@@ -84,6 +133,115 @@ impl User {
             .get_result::<User>(conn)
     }
 
+    pub fn is_admin(&self) -> bool {
+        self.role == ADMIN
+    }
+
+    pub fn is_moderator(&self) -> bool {
+        self.role == MODERATOR || self.is_admin()
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended_at.is_some()
+    }
+
+    /// Whether the user with `user_id` is suspended, without loading them.
+    pub fn is_suspended_id(conn: &PgConnection, user_id: i32) -> Result<bool, Error> {
+        diesel::select(diesel::dsl::exists(
+            users::table
+                .find(user_id)
+                .filter(users::suspended_at.is_not_null()),
+        ))
+        .get_result(conn)
+    }
+
+    /// Suspends the user, or lifts a suspension with `false`.
+    pub fn set_suspended(
+        conn: &PgConnection,
+        user_id: i32,
+        suspended: bool,
+    ) -> Result<Self, Error> {
+        let suspended_at = if suspended { Some(Utc::now()) } else { None };
+        diesel::update(users::table.find(user_id))
+            .set(users::suspended_at.eq(suspended_at))
+            .get_result::<User>(conn)
+    }
+
+    pub fn set_role(conn: &PgConnection, username: &str, role: &str) -> Result<Self, Error> {
+        diesel::update(users::table.filter(users::username.eq(username)))
+            .set(users::role.eq(role))
+            .get_result::<User>(conn)
+    }
+
+    /// Replaces the processed avatar, pointing `image` at its largest PNG so
+    /// clients that only know `image` show it too. `None` removes it, and
+    /// clears `image` if it was still pointing at the old avatar.
+    pub fn set_avatar(
+        conn: &PgConnection,
+        user_id: i32,
+        hashes: Option<&Avatar>,
+    ) -> Result<Self, Error> {
+        let user = User::read(conn, user_id)?;
+        let image = match hashes {
+            Some(hashes) => largest_png(hashes),
+            None if user.image.is_some()
+                && user.image == user.avatar_hashes().as_ref().and_then(largest_png) =>
+            {
+                None
+            }
+            None => user.image,
+        };
+        diesel::update(users::table.find(user_id))
+            .set((
+                users::avatar.eq(hashes.map(|h| serde_json::to_value(h).unwrap())),
+                users::image.eq(image),
+            ))
+            .get_result::<User>(conn)
+    }
+
+    /// The upload hashes of the processed avatar, if there is one.
+    pub fn avatar_hashes(&self) -> Option<Avatar> {
+        self.avatar
+            .clone()
+            .and_then(|avatar| serde_json::from_value(avatar).ok())
+    }
+
+    /// The avatar at every size, falling back to an identicon for users who
+    /// never uploaded one.
+    pub fn avatar(&self) -> Avatar {
+        if let Some(hashes) = self.avatar_hashes() {
+            return hashes
+                .into_iter()
+                .map(|(size, formats)| {
+                    let urls = formats
+                        .into_iter()
+                        .map(|(format, hash)| (format, upload::url(&hash)))
+                        .collect();
+                    (size, urls)
+                })
+                .collect();
+        }
+        let seed = avatar::seed(&self.username);
+        SIZES
+            .iter()
+            .map(|&size| {
+                let urls = Format::ALL
+                    .iter()
+                    .map(|format| {
+                        let url = format!(
+                            "/api/identicons/{}?size={}&format={}",
+                            seed,
+                            size,
+                            format.name()
+                        );
+                        (format.name().to_string(), url)
+                    })
+                    .collect();
+                (size, urls)
+            })
+            .collect()
+    }
+
     pub fn jwt(&self, secret: &str) -> Jwt {
         let exp = Utc::now() + Duration::days(30);
         let my_claims = Claims {
@@ -99,6 +257,7 @@ impl User {
             username: self.username.clone(),
             bio: self.bio.clone(),
             image: self.image.clone(),
+            avatar: self.avatar(),
             following,
         }
     }
@@ -0,0 +1,120 @@
+use crate::schema::*;
+use diesel::{pg::PgConnection, prelude::*, result::Error};
+use std::collections::{HashMap, HashSet};
+
+/// What the current viewer has done with a batch of users and articles,
+/// loaded with one query per relation so responses avoid N+1 lookups.
+#[derive(Debug, Default)]
+pub struct Viewer {
+    id: Option<i32>,
+    following: HashSet<i32>,
+    favorited: HashSet<i32>,
+    bookmarked: HashSet<i32>,
+    article_reactions: HashMap<i32, Vec<String>>,
+    comment_reactions: HashMap<i32, Vec<String>>,
+}
+
+impl Viewer {
+    pub fn load(
+        conn: &PgConnection,
+        viewer: Option<i32>,
+        users: &[i32],
+        articles: &[i32],
+    ) -> Result<Self, Error> {
+        let viewer = match viewer {
+            Some(id) => id,
+            None => return Ok(Viewer::default()),
+        };
+
+        let following = if users.is_empty() {
+            HashSet::new()
+        } else {
+            follows::table
+                .filter(follows::follower.eq(viewer))
+                .filter(follows::followed.eq_any(users))
+                .select(follows::followed)
+                .load::<i32>(conn)?
+                .into_iter()
+                .collect()
+        };
+
+        let (favorited, bookmarked, article_reactions) = if articles.is_empty() {
+            (HashSet::new(), HashSet::new(), HashMap::new())
+        } else {
+            let favorited = favorites::table
+                .filter(favorites::user.eq(viewer))
+                .filter(favorites::article.eq_any(articles))
+                .select(favorites::article)
+                .load::<i32>(conn)?
+                .into_iter()
+                .collect();
+            let bookmarked = bookmarks::table
+                .filter(bookmarks::user.eq(viewer))
+                .filter(bookmarks::article.eq_any(articles))
+                .select(bookmarks::article)
+                .load::<i32>(conn)?
+                .into_iter()
+                .collect();
+            let reactions = article_reactions::table
+                .filter(article_reactions::user.eq(viewer))
+                .filter(article_reactions::article.eq_any(articles))
+                .select((article_reactions::article, article_reactions::reaction))
+                .order(article_reactions::created_at)
+                .load::<(i32, String)>(conn)?;
+            (favorited, bookmarked, group(reactions))
+        };
+
+        Ok(Viewer {
+            id: Some(viewer),
+            following,
+            favorited,
+            bookmarked,
+            article_reactions,
+            comment_reactions: HashMap::new(),
+        })
+    }
+
+    /// Also loads the viewer's reactions to `comments`.
+    pub fn load_comments(&mut self, conn: &PgConnection, comments: &[i32]) -> Result<(), Error> {
+        let viewer = match self.id {
+            Some(id) if !comments.is_empty() => id,
+            _ => return Ok(()),
+        };
+        let reactions = comment_reactions::table
+            .filter(comment_reactions::user.eq(viewer))
+            .filter(comment_reactions::comment.eq_any(comments))
+            .select((comment_reactions::comment, comment_reactions::reaction))
+            .order(comment_reactions::created_at)
+            .load::<(i32, String)>(conn)?;
+        self.comment_reactions = group(reactions);
+        Ok(())
+    }
+
+    pub fn follows(&self, user: i32) -> bool {
+        self.following.contains(&user)
+    }
+
+    pub fn favorited(&self, article: i32) -> bool {
+        self.favorited.contains(&article)
+    }
+
+    pub fn bookmarked(&self, article: i32) -> bool {
+        self.bookmarked.contains(&article)
+    }
+
+    pub fn article_reactions(&self, article: i32) -> Vec<String> {
+        self.article_reactions.get(&article).cloned().unwrap_or_default()
+    }
+
+    pub fn comment_reactions(&self, comment: i32) -> Vec<String> {
+        self.comment_reactions.get(&comment).cloned().unwrap_or_default()
+    }
+}
+
+fn group(rows: Vec<(i32, String)>) -> HashMap<i32, Vec<String>> {
+    let mut grouped: HashMap<i32, Vec<String>> = HashMap::new();
+    for (id, reaction) in rows {
+        grouped.entry(id).or_default().push(reaction);
+    }
+    grouped
+}
@@ -0,0 +1,39 @@
+use crate::schema::*;
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, result::Error};
+use serde::Serialize;
+
+/// A moderator's warning, as shown to the warned user.
+#[derive(Queryable, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Warning {
+    pub id: i32,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub fn create(
+    conn: &PgConnection,
+    user_id: i32,
+    moderator: i32,
+    report: i32,
+    message: &str,
+) -> Result<usize, Error> {
+    diesel::insert_into(warnings::table)
+        .values((
+            warnings::user.eq(user_id),
+            warnings::moderator.eq(moderator),
+            warnings::report.eq(report),
+            warnings::message.eq(message),
+        ))
+        .execute(conn)
+}
+
+/// The warnings `user_id` received, most recent first.
+pub fn list(conn: &PgConnection, user_id: i32) -> Result<Vec<Warning>, Error> {
+    warnings::table
+        .filter(warnings::user.eq(user_id))
+        .select((warnings::id, warnings::message, warnings::created_at))
+        .order(warnings::created_at.desc())
+        .load::<Warning>(conn)
+}
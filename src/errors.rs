@@ -15,6 +15,10 @@ pub enum CliError {
     SerdeJson(JsonError),
     R2d2Error(r2d2::Error),
     EnvError(std::env::VarError),
+    #[display(fmt = "unknown command: {}", _0)]
+    UnknownCommand(String),
+    #[display(fmt = "usage: {}", _0)]
+    Usage(&'static str),
 }
 
 impl std::error::Error for CliError {}
@@ -52,12 +56,29 @@ impl Errors {
     pub fn insert_error(&mut self, field: &'static str, error: &str) {
         self.errors.insert(field, vec![error.to_string()]);
     }
+
+    pub fn push_error(&mut self, field: &'static str, error: &str) {
+        self.errors
+            .entry(field)
+            .or_default()
+            .push(error.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
 }
 
 
 impl fmt::Display for Errors {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_string())
+        let mut fields: Vec<_> = self.errors.iter().collect();
+        fields.sort();
+        let messages: Vec<String> = fields
+            .into_iter()
+            .flat_map(|(field, errors)| errors.iter().map(move |e| format!("{} {}", field, e)))
+            .collect();
+        write!(f, "{}", messages.join("; "))
     }
 }
 
@@ -101,6 +122,13 @@ impl From<DieselError> for Errors {
             match info.constraint_name() {
                 Some("users_username_key") => errors.insert_error("username", "duplicated"),
                 Some("users_email_key") => errors.insert_error("email", "duplicated"),
+                Some("articles_slug_key") => errors.insert_error("slug", "duplicated"),
+                Some("series_articles_article_key") => {
+                    errors.insert_error("articles", "already part of another series")
+                }
+                Some("reports_open_key") => {
+                    errors.insert_error("targetId", "already reported and awaiting review")
+                }
                 _ => errors.insert_error("constraint", "data already exists"),
             }
         } else {
@@ -120,6 +148,15 @@ impl From<BlockingError<DieselError>> for Errors {
     }
 }
 
+impl From<BlockingError<Errors>> for Errors {
+    fn from(err: BlockingError<Errors>) -> Self {
+        match err {
+            BlockingError::Error(e) => e,
+            _ => Errors::new(),
+        }
+    }
+}
+
 impl ResponseError for Errors {
     fn error_response(&self) -> web::HttpResponse {
         web::HttpResponse::build(self.status_code).json(self)
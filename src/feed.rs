@@ -0,0 +1,298 @@
+use chrono::{DateTime, TimeZone, Utc};
+use std::fmt::Write;
+
+/// A syndication feed, rendered as either Atom or RSS 2.0.
+pub struct Feed {
+    pub title: String,
+    /// The page the feed mirrors.
+    pub link: String,
+    /// Where the feed itself is served.
+    pub self_link: String,
+    pub entries: Vec<Entry>,
+}
+
+pub struct Entry {
+    /// Stays the same when the article is renamed, unlike `link`.
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    pub summary: String,
+    /// Rendered, sanitized HTML.
+    pub content: String,
+    pub author: String,
+    pub author_link: String,
+    pub published: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub categories: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Atom,
+    Rss,
+}
+
+impl Format {
+    pub fn parse(extension: &str) -> Option<Self> {
+        match extension {
+            "atom" => Some(Format::Atom),
+            "rss" => Some(Format::Rss),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Atom => "atom",
+            Format::Rss => "rss",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Atom => "application/atom+xml; charset=utf-8",
+            Format::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+}
+
+/// Escapes text for use in XML content and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Feed {
+    /// When any entry last changed. Empty feeds use the epoch so they render
+    /// the same on every request.
+    pub fn updated(&self) -> DateTime<Utc> {
+        self.entries
+            .iter()
+            .map(|e| e.updated)
+            .max()
+            .unwrap_or_else(|| Utc.timestamp(0, 0))
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Atom => self.to_atom(),
+            Format::Rss => self.to_rss(),
+        }
+    }
+
+    fn to_atom(&self) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        let _ = writeln!(xml, "  <id>{}</id>", escape(&self.self_link));
+        let _ = writeln!(xml, "  <title>{}</title>", escape(&self.title));
+        let _ = writeln!(xml, "  <updated>{}</updated>", self.updated().to_rfc3339());
+        let _ = writeln!(
+            xml,
+            "  <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>",
+            escape(&self.link)
+        );
+        let _ = writeln!(
+            xml,
+            "  <link rel=\"self\" type=\"application/atom+xml\" href=\"{}\"/>",
+            escape(&self.self_link)
+        );
+        for entry in &self.entries {
+            xml.push_str("  <entry>\n");
+            let _ = writeln!(xml, "    <id>{}</id>", escape(&entry.id));
+            let _ = writeln!(xml, "    <title>{}</title>", escape(&entry.title));
+            let _ = writeln!(
+                xml,
+                "    <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>",
+                escape(&entry.link)
+            );
+            let _ = writeln!(
+                xml,
+                "    <published>{}</published>",
+                entry.published.to_rfc3339()
+            );
+            let _ = writeln!(xml, "    <updated>{}</updated>", entry.updated.to_rfc3339());
+            let _ = writeln!(
+                xml,
+                "    <author><name>{}</name><uri>{}</uri></author>",
+                escape(&entry.author),
+                escape(&entry.author_link)
+            );
+            for category in &entry.categories {
+                let _ = writeln!(xml, "    <category term=\"{}\"/>", escape(category));
+            }
+            let _ = writeln!(xml, "    <summary>{}</summary>", escape(&entry.summary));
+            let _ = writeln!(
+                xml,
+                "    <content type=\"html\">{}</content>",
+                escape(&entry.content)
+            );
+            xml.push_str("  </entry>\n");
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    fn to_rss(&self) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str(
+            "<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" \
+             xmlns:content=\"http://purl.org/rss/1.0/modules/content/\" \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n",
+        );
+        xml.push_str("  <channel>\n");
+        let _ = writeln!(xml, "    <title>{}</title>", escape(&self.title));
+        let _ = writeln!(xml, "    <link>{}</link>", escape(&self.link));
+        let _ = writeln!(
+            xml,
+            "    <description>{}</description>",
+            escape(&self.title)
+        );
+        let _ = writeln!(
+            xml,
+            "    <atom:link rel=\"self\" type=\"application/rss+xml\" href=\"{}\"/>",
+            escape(&self.self_link)
+        );
+        let _ = writeln!(
+            xml,
+            "    <lastBuildDate>{}</lastBuildDate>",
+            self.updated().to_rfc2822()
+        );
+        for entry in &self.entries {
+            xml.push_str("    <item>\n");
+            let _ = writeln!(xml, "      <title>{}</title>", escape(&entry.title));
+            let _ = writeln!(xml, "      <link>{}</link>", escape(&entry.link));
+            let _ = writeln!(
+                xml,
+                "      <guid isPermaLink=\"false\">{}</guid>",
+                escape(&entry.id)
+            );
+            let _ = writeln!(
+                xml,
+                "      <pubDate>{}</pubDate>",
+                entry.published.to_rfc2822()
+            );
+            let _ = writeln!(
+                xml,
+                "      <dc:creator>{}</dc:creator>",
+                escape(&entry.author)
+            );
+            for category in &entry.categories {
+                let _ = writeln!(xml, "      <category>{}</category>", escape(category));
+            }
+            let _ = writeln!(
+                xml,
+                "      <description>{}</description>",
+                escape(&entry.summary)
+            );
+            let _ = writeln!(
+                xml,
+                "      <content:encoded>{}</content:encoded>",
+                escape(&entry.content)
+            );
+            xml.push_str("    </item>\n");
+        }
+        xml.push_str("  </channel>\n");
+        xml.push_str("</rss>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(entries: Vec<Entry>) -> Feed {
+        Feed {
+            title: "Articles & more".to_string(),
+            link: "https://example.com/".to_string(),
+            self_link: "https://example.com/feeds/articles.atom".to_string(),
+            entries,
+        }
+    }
+
+    fn entry() -> Entry {
+        let published = Utc.ymd(2026, 10, 19).and_hms(8, 0, 0);
+        Entry {
+            id: "tag:example.com,2026-10-19:article-1".to_string(),
+            title: "<script>alert(\"x\")</script>".to_string(),
+            link: "https://example.com/article/a?b=1&c=2".to_string(),
+            summary: "Tom's & Jerry's".to_string(),
+            content: "<p>Hello <em>world</em></p>".to_string(),
+            author: "alice".to_string(),
+            author_link: "https://example.com/profile/alice".to_string(),
+            published,
+            updated: published,
+            categories: vec!["r&d".to_string()],
+        }
+    }
+
+    #[test]
+    fn escapes_markup_and_quotes() {
+        assert_eq!(
+            escape("<a href=\"x\">Tom's & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom&apos;s &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+        assert_eq!(escape("plain"), "plain");
+    }
+
+    #[test]
+    fn escapes_entries_in_both_formats() {
+        for format in &[Format::Atom, Format::Rss] {
+            let xml = feed(vec![entry()]).render(*format);
+            assert!(!xml.contains("<script>"), "{:?}", format);
+            assert!(xml.contains("&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt;"));
+            assert!(xml.contains("&lt;p&gt;Hello &lt;em&gt;world&lt;/em&gt;&lt;/p&gt;"));
+            assert!(xml.contains("https://example.com/article/a?b=1&amp;c=2"));
+            assert!(xml.contains("Articles &amp; more"));
+            assert!(xml.contains("r&amp;d"));
+        }
+    }
+
+    #[test]
+    fn identifies_entries_by_id() {
+        let atom = feed(vec![entry()]).render(Format::Atom);
+        assert!(atom.contains("<id>tag:example.com,2026-10-19:article-1</id>"));
+        let rss = feed(vec![entry()]).render(Format::Rss);
+        assert!(rss.contains(
+            "<guid isPermaLink=\"false\">tag:example.com,2026-10-19:article-1</guid>"
+        ));
+    }
+
+    #[test]
+    fn renders_empty_feeds_the_same_every_time() {
+        let empty = feed(Vec::new());
+        assert_eq!(empty.updated(), Utc.timestamp(0, 0));
+
+        let atom = empty.render(Format::Atom);
+        assert!(atom.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n"));
+        assert!(atom.contains("<updated>1970-01-01T00:00:00+00:00</updated>"));
+        assert!(!atom.contains("<entry>"));
+        assert!(atom.ends_with("</feed>\n"));
+
+        let rss = empty.render(Format::Rss);
+        assert!(rss.contains("<lastBuildDate>Thu, 01 Jan 1970 00:00:00 +0000</lastBuildDate>"));
+        assert!(!rss.contains("<item>"));
+        assert!(rss.ends_with("</rss>\n"));
+        assert_eq!(rss, feed(Vec::new()).render(Format::Rss));
+    }
+
+    #[test]
+    fn parses_extensions() {
+        assert_eq!(Format::parse("atom"), Some(Format::Atom));
+        assert_eq!(Format::parse("rss"), Some(Format::Rss));
+        assert_eq!(Format::parse("xml"), None);
+        assert_eq!(Format::Rss.extension(), "rss");
+    }
+}
@@ -0,0 +1,383 @@
+use crate::db::{activity, User};
+use chrono::{Duration, Utc};
+use diesel::{pg::PgConnection, result::Error};
+
+/// What a filter makes of a submission. The pipeline settles on the
+/// strictest verdict any filter gives.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Allow,
+    /// Publish it hidden, pending a moderator's review.
+    Hold(String),
+    /// Refuse it, naming the field at fault and why.
+    Reject(&'static str, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Article,
+    Comment,
+}
+
+/// An article or comment about to be written.
+pub struct Submission<'a> {
+    pub kind: Kind,
+    pub author: &'a User,
+    /// Every piece of text, such as the title, description and body, with
+    /// the name of its field.
+    pub texts: Vec<(&'static str, &'a str)>,
+    /// The body, compared against the author's earlier posts. `None` when an
+    /// edit leaves it as is.
+    pub body: Option<&'a str>,
+    /// The slug of the article being edited.
+    pub editing: Option<&'a str>,
+}
+
+/// A check run on every article and comment write.
+pub trait ContentFilter: Send + Sync {
+    fn check(&self, conn: &PgConnection, submission: &Submission) -> Result<Verdict, Error>;
+}
+
+#[derive(Default)]
+pub struct Pipeline {
+    filters: Vec<Box<dyn ContentFilter>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    pub fn with<F: ContentFilter + 'static>(mut self, filter: F) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Runs every filter, stopping at the first rejection. Reasons for
+    /// holding are collected for the moderator.
+    pub fn check(&self, conn: &PgConnection, submission: &Submission) -> Result<Verdict, Error> {
+        let mut held = Vec::new();
+        for filter in &self.filters {
+            match filter.check(conn, submission)? {
+                Verdict::Allow => {}
+                Verdict::Hold(reason) => held.push(reason),
+                reject => return Ok(reject),
+            }
+        }
+        if held.is_empty() {
+            Ok(Verdict::Allow)
+        } else {
+            Ok(Verdict::Hold(held.join("; ")))
+        }
+    }
+}
+
+/// Holds text with more than `max` links.
+pub struct LinkCount {
+    pub max: usize,
+}
+
+impl ContentFilter for LinkCount {
+    fn check(&self, _conn: &PgConnection, submission: &Submission) -> Result<Verdict, Error> {
+        let links: usize = submission
+            .texts
+            .iter()
+            .map(|(_, text)| {
+                let text = text.to_lowercase();
+                text.matches("http://").count() + text.matches("https://").count()
+            })
+            .sum();
+        if links > self.max {
+            return Ok(Verdict::Hold(format!("contains {} links", links)));
+        }
+        Ok(Verdict::Allow)
+    }
+}
+
+/// Rejects text containing any of the configured words or phrases,
+/// ignoring case.
+pub struct BlockedWords {
+    words: Vec<String>,
+}
+
+impl BlockedWords {
+    pub fn new(words: &[String]) -> Self {
+        BlockedWords {
+            words: words.iter().map(|w| w.to_lowercase()).collect(),
+        }
+    }
+
+    fn contains(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        let tokens: Vec<&str> = text
+            .split(|c: char| !c.is_alphanumeric() && c != '\'')
+            .collect();
+        self.words.iter().any(|word| {
+            if word.contains(' ') {
+                text.contains(word.as_str())
+            } else {
+                tokens.contains(&word.as_str())
+            }
+        })
+    }
+}
+
+impl ContentFilter for BlockedWords {
+    fn check(&self, _conn: &PgConnection, submission: &Submission) -> Result<Verdict, Error> {
+        match submission
+            .texts
+            .iter()
+            .find(|(_, text)| self.contains(text))
+        {
+            Some((field, _)) => Ok(Verdict::Reject(field, "contains blocked words".to_string())),
+            None => Ok(Verdict::Allow),
+        }
+    }
+}
+
+/// Rejects a body the author already posted within `window`.
+pub struct Duplicate {
+    pub window: Duration,
+}
+
+impl ContentFilter for Duplicate {
+    fn check(&self, conn: &PgConnection, submission: &Submission) -> Result<Verdict, Error> {
+        let body = match submission.body {
+            Some(body) if !body.trim().is_empty() => body,
+            _ => return Ok(Verdict::Allow),
+        };
+        let since = Utc::now() - self.window;
+        let author = submission.author.id;
+        let duplicate = match submission.kind {
+            Kind::Article => {
+                activity::article_duplicate(conn, author, body, since, submission.editing)?
+            }
+            Kind::Comment => activity::comment_duplicate(conn, author, body, since)?,
+        };
+        if duplicate {
+            return Ok(Verdict::Reject("body", "was already posted".to_string()));
+        }
+        Ok(Verdict::Allow)
+    }
+}
+
+/// Holds new posts by accounts younger than `account_age` once they wrote
+/// `per_hour` articles and comments in the last hour. Edits are let
+/// through.
+pub struct NewAccountRate {
+    pub account_age: Duration,
+    pub per_hour: i64,
+}
+
+impl ContentFilter for NewAccountRate {
+    fn check(&self, conn: &PgConnection, submission: &Submission) -> Result<Verdict, Error> {
+        let now = Utc::now();
+        if submission.editing.is_some() || submission.author.created_at < now - self.account_age {
+            return Ok(Verdict::Allow);
+        }
+        let posts = activity::posts_since(conn, submission.author.id, now - Duration::hours(1))?;
+        if posts >= self.per_hour {
+            return Ok(Verdict::Hold(format!(
+                "new account posted {} times in the last hour",
+                posts
+            )));
+        }
+        Ok(Verdict::Allow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{articles, comments, users};
+    use diesel::prelude::*;
+
+    /// The database from `.env`. Tests run in a transaction that is never
+    /// committed.
+    fn connection() -> PgConnection {
+        dotenv::dotenv().ok();
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        PgConnection::establish(&url).unwrap()
+    }
+
+    fn user(conn: &PgConnection, name: &str, age: Duration) -> User {
+        diesel::insert_into(users::table)
+            .values((
+                users::username.eq(name),
+                users::email.eq(format!("{}@filter.test", name)),
+                users::password.eq(""),
+                users::created_at.eq(Utc::now() - age),
+            ))
+            .get_result(conn)
+            .unwrap()
+    }
+
+    fn article(conn: &PgConnection, author: &User, slug: &str, body: &str) -> i32 {
+        diesel::insert_into(articles::table)
+            .values((
+                articles::slug.eq(slug),
+                articles::title.eq(slug),
+                articles::description.eq(""),
+                articles::body.eq(body),
+                articles::author.eq(author.id),
+                articles::tag_list.eq(Vec::<String>::new()),
+            ))
+            .returning(articles::id)
+            .get_result(conn)
+            .unwrap()
+    }
+
+    fn submission<'a>(kind: Kind, author: &'a User, body: &'a str) -> Submission<'a> {
+        Submission {
+            kind,
+            author,
+            texts: vec![("title", "A title"), ("body", body)],
+            body: Some(body),
+            editing: None,
+        }
+    }
+
+    #[test]
+    fn link_count_holds_past_the_limit() {
+        let conn = connection();
+        conn.test_transaction::<_, Error, _>(|| {
+            let author = user(&conn, "links", Duration::days(30));
+            let filter = LinkCount { max: 2 };
+            let two = submission(Kind::Article, &author, "http://a.test https://b.test");
+            assert_eq!(filter.check(&conn, &two)?, Verdict::Allow);
+            let three = submission(Kind::Comment, &author, "http://a HTTPS://b https://c");
+            assert_eq!(
+                filter.check(&conn, &three)?,
+                Verdict::Hold("contains 3 links".to_string())
+            );
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn blocked_words_reject_the_field_they_are_in() {
+        let conn = connection();
+        conn.test_transaction::<_, Error, _>(|| {
+            let author = user(&conn, "words", Duration::days(30));
+            let filter = BlockedWords::new(&["casino".to_string(), "free money".to_string()]);
+            let mut post = submission(Kind::Article, &author, "Visit the CASINO!");
+            assert_eq!(
+                filter.check(&conn, &post)?,
+                Verdict::Reject("body", "contains blocked words".to_string())
+            );
+            post.texts = vec![("title", "Free  money? Free money."), ("body", "")];
+            assert_eq!(
+                filter.check(&conn, &post)?,
+                Verdict::Reject("title", "contains blocked words".to_string())
+            );
+            post.texts = vec![("body", "Casinos and casino-free zones")];
+            assert_eq!(
+                filter.check(&conn, &post)?,
+                Verdict::Reject("body", "contains blocked words".to_string())
+            );
+            post.texts = vec![("body", "Casinos are not mentioned here")];
+            assert_eq!(filter.check(&conn, &post)?, Verdict::Allow);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn duplicate_rejects_bodies_posted_within_the_window() {
+        let conn = connection();
+        conn.test_transaction::<_, Error, _>(|| {
+            let author = user(&conn, "duplicate", Duration::days(30));
+            let id = article(&conn, &author, "filter-original", "Same words");
+            diesel::insert_into(comments::table)
+                .values((
+                    comments::body.eq("Same comment"),
+                    comments::author.eq(author.id),
+                    comments::article.eq(id),
+                ))
+                .execute(&conn)?;
+            let filter = Duplicate {
+                window: Duration::hours(1),
+            };
+            let rejected = Verdict::Reject("body", "was already posted".to_string());
+
+            let mut post = submission(Kind::Article, &author, "Same words");
+            assert_eq!(filter.check(&conn, &post)?, rejected);
+            post.editing = Some("filter-original");
+            assert_eq!(filter.check(&conn, &post)?, Verdict::Allow);
+
+            let comment = submission(Kind::Comment, &author, "Same comment");
+            assert_eq!(filter.check(&conn, &comment)?, rejected);
+            let other = submission(Kind::Comment, &author, "Something else");
+            assert_eq!(filter.check(&conn, &other)?, Verdict::Allow);
+
+            let mut unchanged = submission(Kind::Article, &author, "Same words");
+            unchanged.body = None;
+            assert_eq!(filter.check(&conn, &unchanged)?, Verdict::Allow);
+
+            let expired = Duplicate {
+                window: Duration::zero(),
+            };
+            let post = submission(Kind::Article, &author, "Same words");
+            assert_eq!(expired.check(&conn, &post)?, Verdict::Allow);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn new_account_rate_holds_new_accounts_posting_fast() {
+        let conn = connection();
+        conn.test_transaction::<_, Error, _>(|| {
+            let filter = NewAccountRate {
+                account_age: Duration::hours(24),
+                per_hour: 2,
+            };
+            let new = user(&conn, "newcomer", Duration::hours(1));
+            article(&conn, &new, "filter-first", "one");
+            let post = submission(Kind::Comment, &new, "two");
+            assert_eq!(filter.check(&conn, &post)?, Verdict::Allow);
+
+            article(&conn, &new, "filter-second", "two");
+            assert_eq!(
+                filter.check(&conn, &post)?,
+                Verdict::Hold("new account posted 2 times in the last hour".to_string())
+            );
+            let mut edit = submission(Kind::Article, &new, "three");
+            edit.editing = Some("filter-second");
+            assert_eq!(filter.check(&conn, &edit)?, Verdict::Allow);
+
+            let old = user(&conn, "regular", Duration::days(2));
+            article(&conn, &old, "filter-third", "one");
+            article(&conn, &old, "filter-fourth", "two");
+            let post = submission(Kind::Comment, &old, "three");
+            assert_eq!(filter.check(&conn, &post)?, Verdict::Allow);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn pipeline_rejects_first_and_collects_holds() {
+        let conn = connection();
+        conn.test_transaction::<_, Error, _>(|| {
+            let author = user(&conn, "pipeline", Duration::days(30));
+            let pipeline = Pipeline::new()
+                .with(LinkCount { max: 0 })
+                .with(BlockedWords::new(&["casino".to_string()]))
+                .with(LinkCount { max: 1 });
+
+            let fine = submission(Kind::Article, &author, "Nothing to see");
+            assert_eq!(pipeline.check(&conn, &fine)?, Verdict::Allow);
+
+            let links = submission(Kind::Article, &author, "http://a http://b");
+            assert_eq!(
+                pipeline.check(&conn, &links)?,
+                Verdict::Hold("contains 2 links; contains 2 links".to_string())
+            );
+
+            let blocked = submission(Kind::Article, &author, "http://casino");
+            assert_eq!(
+                pipeline.check(&conn, &blocked)?,
+                Verdict::Reject("body", "contains blocked words".to_string())
+            );
+            Ok(())
+        });
+    }
+}
@@ -13,16 +13,32 @@ use diesel::pg::PgConnection;
 use diesel::r2d2::{self, ConnectionManager};
 use dotenv::dotenv;
 use std::net::IpAddr;
-use std::{env, fmt};
+use std::{env, fmt, sync::Arc, time::Duration};
 
 pub mod api;
+pub mod archive;
 pub mod auth;
+pub mod avatar;
+pub mod card;
+pub mod cli;
 pub mod db;
 pub mod errors;
+pub mod feed;
+pub mod filter;
+pub mod markdown;
 pub mod models;
 pub mod schema;
+pub mod storage;
+pub mod trash;
+pub mod validation;
+pub mod views;
 
 use errors::CliError;
+use api::{meta::Site, moderation::ModerationConfig, reactions::Reactions, uploads::UploadConfig};
+use card::CardRenderer;
+use filter::{BlockedWords, Duplicate, LinkCount, NewAccountRate, Pipeline};
+use storage::LocalStore;
+use views::ViewTracker;
 
 pub struct Settings {
     pub database_url: String,
@@ -30,6 +46,19 @@ pub struct Settings {
     pub hostname: String,
     pub bind: IpAddr,
     pub port: u16,
+    pub view_flush_interval: u64,
+    pub reactions: Vec<String>,
+    pub upload_dir: String,
+    pub upload_max_size: usize,
+    pub upload_quota: i64,
+    pub card_font: String,
+    pub report_hide_threshold: i64,
+    pub filter_max_links: usize,
+    pub blocked_words: Vec<String>,
+    pub duplicate_window_hours: i64,
+    pub new_account_hours: i64,
+    pub new_account_posts_per_hour: i64,
+    pub trash_retention_days: i64,
 }
 
 impl Settings {
@@ -47,6 +76,60 @@ impl Settings {
                 .unwrap_or_else(|_| "8088".to_string())
                 .parse()
                 .unwrap(),
+            view_flush_interval: env::var("VIEW_FLUSH_INTERVAL")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap(),
+            reactions: env::var("REACTIONS")
+                .unwrap_or_else(|_| "👍,❤️,🎉,🤔".to_string())
+                .split(',')
+                .map(|r| r.trim().to_string())
+                .filter(|r| !r.is_empty())
+                .collect(),
+            upload_dir: env::var("UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string()),
+            upload_max_size: env::var("UPLOAD_MAX_SIZE")
+                .unwrap_or_else(|_| (5 * 1024 * 1024).to_string())
+                .parse()
+                .unwrap(),
+            upload_quota: env::var("UPLOAD_QUOTA")
+                .unwrap_or_else(|_| (100 * 1024 * 1024).to_string())
+                .parse()
+                .unwrap(),
+            card_font: env::var("CARD_FONT").unwrap_or_else(|_| {
+                "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf".to_string()
+            }),
+            report_hide_threshold: env::var("REPORT_HIDE_THRESHOLD")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap(),
+            filter_max_links: env::var("FILTER_MAX_LINKS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap(),
+            blocked_words: env::var("BLOCKED_WORDS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|w| w.trim().to_string())
+                .filter(|w| !w.is_empty())
+                .collect(),
+            duplicate_window_hours: env::var("DUPLICATE_WINDOW_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .unwrap(),
+            new_account_hours: env::var("NEW_ACCOUNT_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .unwrap(),
+            new_account_posts_per_hour: env::var("NEW_ACCOUNT_POSTS_PER_HOUR")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap(),
+            trash_retention_days: env::var("TRASH_RETENTION_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .ok()
+                .filter(|days| (0..=trash::MAX_RETENTION_DAYS).contains(days))
+                .expect("TRASH_RETENTION_DAYS must be between 0 and 36500"),
         }
     }
 }
@@ -72,10 +155,71 @@ fn db_pool(database_url: &str) -> Result<Pool, CliError> {
 
 pub async fn run(settings: Settings) -> Result<(), errors::CliError> {
     let pool = db_pool(&settings.database_url)?;
+    let views = web::Data::new(ViewTracker::new(&settings.jwt_secret));
 
+    {
+        let views = views.clone();
+        let pool = pool.clone();
+        let period = Duration::from_secs(settings.view_flush_interval);
+        actix_rt::spawn(async move {
+            let mut interval = actix_rt::time::interval(period);
+            loop {
+                interval.tick().await;
+                views.flush(pool.clone()).await;
+            }
+        });
+    }
+
+    {
+        let pool = pool.clone();
+        let retention = chrono::Duration::days(settings.trash_retention_days);
+        let period = Duration::from_secs(trash::PURGE_INTERVAL_SECS);
+        actix_rt::spawn(async move {
+            let mut interval = actix_rt::time::interval(period);
+            loop {
+                interval.tick().await;
+                trash::purge_in_background(pool.clone(), retention).await;
+            }
+        });
+    }
+
+    let reactions = web::Data::new(Reactions::new(settings.reactions.clone()));
+    let uploads = web::Data::new(UploadConfig {
+        store: Arc::new(LocalStore::new(&settings.upload_dir)),
+        max_size: settings.upload_max_size,
+        quota: settings.upload_quota,
+    });
+    let site = web::Data::new(Site::new(&settings.hostname));
+    let cards = web::Data::new(CardRenderer::load(&settings.card_font));
+    let moderation = web::Data::new(ModerationConfig {
+        hide_after: settings.report_hide_threshold,
+    });
+    let filters = web::Data::new(
+        Pipeline::new()
+            .with(LinkCount {
+                max: settings.filter_max_links,
+            })
+            .with(BlockedWords::new(&settings.blocked_words))
+            .with(Duplicate {
+                window: chrono::Duration::hours(settings.duplicate_window_hours),
+            })
+            .with(NewAccountRate {
+                account_age: chrono::Duration::hours(settings.new_account_hours),
+                per_hour: settings.new_account_posts_per_hour,
+            }),
+    );
+    let app_views = views.clone();
+    let app_pool = pool.clone();
     HttpServer::new(move || {
         App::new()
-            .data(pool.clone())
+            .data(app_pool.clone())
+            .app_data(app_views.clone())
+            .app_data(reactions.clone())
+            .app_data(uploads.clone())
+            .app_data(site.clone())
+            .app_data(cards.clone())
+            .app_data(moderation.clone())
+            .app_data(filters.clone())
             .wrap(Cors::new().max_age(3600).finish())
             .wrap(middleware::Logger::new("%a \"%r\" :: %s :: %b bytes %T"))
             .service(
@@ -84,9 +228,15 @@ pub async fn run(settings: Settings) -> Result<(), errors::CliError> {
                     .service(api::users::login)
                     .service(api::users::get_user)
                     .service(api::users::put_user)
+                    .service(api::users::upload_avatar)
+                    .service(api::users::delete_avatar)
+                    .service(api::feeds::get_feed_token)
+                    .service(api::feeds::regenerate_feed_token)
                     .service(api::profile::get_profiles)
                     .service(api::profile::follow)
                     .service(api::profile::unfollow)
+                    .service(api::profile::favorites)
+                    .service(api::profile::identicon)
                     .service(api::articles::list_articles)
                     .service(api::articles::feed_articles)
                     .service(api::articles::get_article)
@@ -96,14 +246,66 @@ pub async fn run(settings: Settings) -> Result<(), errors::CliError> {
                     .service(api::articles::add_comment)
                     .service(api::articles::get_comments)
                     .service(api::articles::delete_comment)
+                    .service(api::articles::trash)
+                    .service(api::articles::restore_article)
+                    .service(api::articles::restore_comment)
                     .service(api::articles::favorite)
                     .service(api::articles::unfavorite)
-                    .service(api::articles::tags),
+                    .service(api::articles::list_favorites)
+                    .service(api::articles::related_articles)
+                    .service(api::meta::article_meta)
+                    .service(api::meta::article_card)
+                    .service(api::reactions::react_article)
+                    .service(api::reactions::react_comment)
+                    .service(api::articles::bookmark)
+                    .service(api::articles::unbookmark)
+                    .service(api::articles::bookmarks)
+                    .service(api::articles::list_authors)
+                    .service(api::articles::invite_author)
+                    .service(api::articles::accept_author)
+                    .service(api::articles::remove_author)
+                    .service(api::articles::invitations)
+                    .service(api::articles::list_revisions)
+                    .service(api::articles::diff_revisions)
+                    .service(api::articles::restore_revision)
+                    .service(api::series::list_series)
+                    .service(api::series::get_series)
+                    .service(api::series::create_series)
+                    .service(api::series::update_series)
+                    .service(api::series::delete_series)
+                    .service(api::uploads::create_upload)
+                    .service(api::uploads::get_upload)
+                    .service(api::uploads::list_uploads)
+                    .service(api::uploads::delete_upload)
+                    .service(api::tags::tags)
+                    .service(api::tags::trending)
+                    .service(api::tags::get_tag)
+                    .service(api::tags::update_tag)
+                    .service(api::tags::follow)
+                    .service(api::tags::unfollow)
+                    .service(api::tags::followed)
+                    .service(api::stats::user_stats)
+                    .service(api::admin::export)
+                    .service(api::admin::import)
+                    .service(api::moderation::create_report)
+                    .service(api::moderation::list_reports)
+                    .service(api::moderation::get_report)
+                    .service(api::moderation::resolve_report)
+                    .service(api::moderation::unsuspend)
+                    .service(api::moderation::warnings),
             )
+            .service(api::feeds::articles_feed)
+            .service(api::feeds::author_feed)
+            .service(api::feeds::tag_feed)
+            .service(api::feeds::personal_feed)
+            .service(api::sitemaps::sitemap_index)
+            .service(api::sitemaps::sitemap_page)
     })
     .bind((settings.bind, settings.port))?
     .run()
     .await?;
 
+    views.flush(pool).await;
+
     Ok(())
 }
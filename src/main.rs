@@ -10,7 +10,12 @@ async fn main() -> Result<(), CliError> {
     pretty_env_logger::init();
 
     let settings = Settings::get();
-    realworld::run(settings).await?;
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        realworld::run(settings).await?;
+    } else {
+        realworld::cli::run(&settings, &args)?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,339 @@
+use ammonia::Builder;
+use pulldown_cmark::{
+    html, CodeBlockKind, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use syntect::{
+    html::{ClassStyle, ClassedHTMLGenerator},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// Prefixed to every `id` in rendered HTML so that user-written ids cannot
+/// clash with those of the page the article is embedded in.
+const ID_PREFIX: &str = "user-content-";
+
+fn sanitizer() -> &'static Builder<'static> {
+    static SANITIZER: OnceLock<Builder<'static>> = OnceLock::new();
+    SANITIZER.get_or_init(|| {
+        let mut builder = Builder::default();
+        builder
+            .id_prefix(Some(ID_PREFIX))
+            .add_tags(&["input"])
+            .add_tag_attributes("input", &["type", "checked", "disabled"])
+            .add_tag_attributes("pre", &["class"])
+            .add_tag_attributes("code", &["class"])
+            .add_tag_attributes("span", &["class"])
+            .add_tag_attributes("sup", &["class"])
+            .add_tag_attributes("div", &["class", "id"]);
+        for heading in &["h1", "h2", "h3", "h4", "h5", "h6"] {
+            builder.add_tag_attributes(heading, &["id"]);
+        }
+        builder
+    })
+}
+
+fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+/// Gives each heading a unique anchor derived from its text, carrying the
+/// prefix the sanitizer puts on ids.
+struct Anchors(HashMap<String, usize>);
+
+impl Anchors {
+    fn new() -> Self {
+        Anchors(HashMap::new())
+    }
+
+    fn anchor(&mut self, text: &str) -> String {
+        let mut base = slug::slugify(text);
+        if base.is_empty() {
+            base = "section".to_string();
+        }
+        let seen = self.0.entry(base.clone()).or_insert(0);
+        *seen += 1;
+        match *seen {
+            1 => format!("{}{}", ID_PREFIX, base),
+            n => format!("{}{}-{}", ID_PREFIX, base, n - 1),
+        }
+    }
+}
+
+fn highlight(code: &str, lang: &str) -> String {
+    let lang: String = lang
+        .chars()
+        .take_while(|c| !c.is_whitespace())
+        .filter(|c| c.is_ascii_alphanumeric() || "+-_#".contains(*c))
+        .collect();
+    let set = syntax_set();
+    let syntax = set
+        .find_syntax_by_token(&lang)
+        .unwrap_or_else(|| set.find_syntax_plain_text());
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, set, ClassStyle::Spaced);
+    let highlighted = LinesWithEndings::from(code)
+        .try_for_each(|line| generator.parse_html_for_line_which_includes_newline(line))
+        .map(|_| generator.finalize())
+        .unwrap_or_else(|_| ammonia::clean_text(code));
+
+    if lang.is_empty() {
+        format!("<pre><code>{}</code></pre>\n", highlighted)
+    } else {
+        format!(
+            "<pre><code class=\"language-{}\">{}</code></pre>\n",
+            lang, highlighted
+        )
+    }
+}
+
+/// A heading of the document, as listed in a table of contents.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Heading {
+    pub level: u8,
+    pub title: String,
+    pub anchor: String,
+}
+
+/// Reading metadata derived from a Markdown body.
+#[derive(Debug)]
+pub struct Outline {
+    pub word_count: i32,
+    pub reading_time: i32,
+    pub toc: Vec<Heading>,
+}
+
+const WORDS_PER_MINUTE: i32 = 200;
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+fn footnote_id(name: &str) -> CowStr<'static> {
+    CowStr::from(format!("{}{}", ID_PREFIX, name))
+}
+
+/// Parses `body`, highlighting code blocks and anchoring headings. Returns
+/// the events to render together with the headings and the word count.
+fn process(body: &str) -> (Vec<Event<'_>>, Vec<Heading>, usize) {
+    let mut anchors = Anchors::new();
+    let mut events = vec![];
+    let mut headings = vec![];
+    let mut words = 0;
+    let mut heading: Option<(HeadingLevel, Vec<Event>, String)> = None;
+    let mut code: Option<(String, String)> = None;
+
+    for event in Parser::new_ext(body, options()) {
+        if let Event::Text(ref t) | Event::Code(ref t) = event {
+            words += t.split_whitespace().count();
+        }
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                heading = Some((level, vec![], String::new()));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, inner, title)) = heading.take() {
+                    let anchor = anchors.anchor(&title);
+                    events.push(Event::Start(Tag::Heading {
+                        level,
+                        id: Some(CowStr::from(anchor.clone())),
+                        classes: vec![],
+                        attrs: vec![],
+                    }));
+                    events.extend(inner);
+                    events.push(Event::End(TagEnd::Heading(level)));
+                    headings.push(Heading {
+                        level: heading_level(level),
+                        title,
+                        anchor,
+                    });
+                }
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => lang.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((lang, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((lang, source)) = code.take() {
+                    events.push(Event::Html(CowStr::from(highlight(&source, &lang))));
+                }
+            }
+            // Footnote ids get the sanitizer's prefix up front so the links
+            // to them point at the ids that end up in the HTML.
+            Event::FootnoteReference(name) => {
+                events.push(Event::FootnoteReference(footnote_id(&name)));
+            }
+            Event::Start(Tag::FootnoteDefinition(name)) => {
+                events.push(Event::Start(Tag::FootnoteDefinition(footnote_id(&name))));
+            }
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, ref mut source)) = code {
+                    source.push_str(&text);
+                }
+            }
+            event => match heading {
+                Some((_, ref mut inner, ref mut title)) => {
+                    if let Event::Text(ref t) | Event::Code(ref t) = event {
+                        title.push_str(t);
+                    }
+                    inner.push(event);
+                }
+                None => events.push(event),
+            },
+        }
+    }
+
+    (events, headings, words)
+}
+
+/// Renders CommonMark with GFM extensions into sanitized HTML, highlighting
+/// fenced code blocks and adding anchors to headings.
+pub fn render(body: &str) -> String {
+    let (events, _, _) = process(body);
+    let mut unsafe_html = String::with_capacity(body.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, events.into_iter());
+    sanitizer().clean(&unsafe_html).to_string()
+}
+
+/// Computes the word count, the estimated reading time in minutes and the
+/// table of contents of a Markdown body. Anchors match those of `render`.
+pub fn outline(body: &str) -> Outline {
+    let (_, toc, words) = process(body);
+    let word_count = words as i32;
+    let reading_time = (word_count + WORDS_PER_MINUTE - 1) / WORDS_PER_MINUTE;
+    Outline {
+        word_count,
+        reading_time,
+        toc,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_scripts_and_event_handlers() {
+        let html = render("<script>alert(1)</script>\n\n<img src=\"x.png\" onerror=\"alert(1)\">");
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("alert(1)</script"));
+        assert!(!html.contains("onerror"));
+        assert!(html.contains("<img src=\"x.png\""));
+    }
+
+    #[test]
+    fn drops_javascript_links() {
+        let html = render("[click](javascript:alert(1)) and [home](https://example.com)");
+        assert!(!html.contains("javascript:"));
+        assert!(html.contains("href=\"https://example.com\""));
+    }
+
+    #[test]
+    fn keeps_gfm_extensions() {
+        let html =
+            render("| a | b |\n|---|---|\n| 1 | 2 |\n\n~~gone~~\n\n- [x] done\n- [ ] todo\n");
+        assert!(html.contains("<table>"));
+        assert!(html.contains("<td>1</td>"));
+        assert!(html.contains("<del>gone</del>"));
+        assert!(html.contains("<input disabled=\"\" type=\"checkbox\" checked=\"\">"));
+    }
+
+    #[test]
+    fn highlights_code_and_escapes_it() {
+        let html = render("```rust\nfn main() { let x = \"<b>\"; }\n```\n");
+        assert!(html.contains("<pre><code class=\"language-rust\">"));
+        assert!(html.contains("<span class=\""));
+        assert!(html.contains("&lt;b&gt;"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn escapes_code_in_unknown_languages() {
+        let html = render("```not-a-language\n<script>alert(1)</script>\n```\n");
+        assert!(html.contains("class=\"language-not-a-language\""));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script"));
+    }
+
+    #[test]
+    fn anchors_headings_uniquely() {
+        let html = render("# Intro\n\n## Intro\n\n### `code` *and* text\n\n# !!!");
+        assert!(html.contains("<h1 id=\"user-content-intro\">Intro</h1>"));
+        assert!(html.contains("<h2 id=\"user-content-intro-1\">Intro</h2>"));
+        assert!(html.contains("<h3 id=\"user-content-code-and-text\">"));
+        assert!(html.contains("<h1 id=\"user-content-section\">"));
+    }
+
+    #[test]
+    fn prefixes_user_written_ids() {
+        let html = render("<div id=\"main\">x</div>\n\n<h2 id=\"top\">y</h2>");
+        assert!(html.contains("<div id=\"user-content-main\">"));
+        assert!(html.contains("<h2 id=\"user-content-top\">"));
+    }
+
+    #[test]
+    fn links_footnotes_to_their_prefixed_ids() {
+        let html = render("Text[^note].\n\n[^note]: The note.\n");
+        assert!(html.contains("href=\"#user-content-note\""));
+        assert!(html.contains("id=\"user-content-note\""));
+    }
+
+    #[test]
+    fn counts_words_outside_markup() {
+        let outline = outline(
+            "# Two words\n\nOne *two* [three](https://x.test) `four`\n\n```\nfive six\n```\n",
+        );
+        assert_eq!(outline.word_count, 8);
+    }
+
+    #[test]
+    fn rounds_reading_time_up_to_the_minute() {
+        let minutes = |words: usize| outline(&"word ".repeat(words)).reading_time;
+        assert_eq!(minutes(0), 0);
+        assert_eq!(minutes(1), 1);
+        assert_eq!(minutes(200), 1);
+        assert_eq!(minutes(201), 2);
+        assert_eq!(minutes(1000), 5);
+    }
+
+    #[test]
+    fn lists_headings_with_the_anchors_render_uses() {
+        let body = "# Intro\n\ntext\n\n## Setup `cargo`\n\n## Intro\n";
+        let toc = outline(body).toc;
+        let listed: Vec<(u8, &str, &str)> = toc
+            .iter()
+            .map(|h| (h.level, h.title.as_str(), h.anchor.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                (1, "Intro", "user-content-intro"),
+                (2, "Setup cargo", "user-content-setup-cargo"),
+                (2, "Intro", "user-content-intro-1"),
+            ]
+        );
+        let html = render(body);
+        for heading in &toc {
+            assert!(html.contains(&format!("id=\"{}\"", heading.anchor)));
+        }
+    }
+}
@@ -1,3 +1,60 @@
+table! {
+    article_authors (article, user) {
+        article -> Int4,
+        user -> Int4,
+        position -> Int4,
+        invited_at -> Timestamptz,
+        accepted_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    article_cards (article) {
+        article -> Int4,
+        hash -> Text,
+        drawn_from -> Text,
+    }
+}
+
+table! {
+    article_reactions (article, user, reaction) {
+        article -> Int4,
+        user -> Int4,
+        reaction -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    article_revisions (id) {
+        id -> Int4,
+        article -> Int4,
+        author -> Int4,
+        title -> Text,
+        description -> Text,
+        body -> Text,
+        changed_fields -> Array<Text>,
+        created_at -> Timestamptz,
+        restored_from -> Nullable<Int4>,
+    }
+}
+
+table! {
+    article_tags (article, tag) {
+        article -> Int4,
+        tag -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    article_views (article, visitor, viewed_on) {
+        article -> Int4,
+        visitor -> Text,
+        viewed_on -> Date,
+    }
+}
+
 table! {
     articles (id) {
         id -> Int4,
@@ -10,6 +67,33 @@ table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         favorites_count -> Int4,
+        body_html -> Nullable<Text>,
+        word_count -> Int4,
+        reading_time -> Int4,
+        toc -> Nullable<Jsonb>,
+        reaction_counts -> Jsonb,
+        cover_image -> Nullable<Text>,
+        hidden_at -> Nullable<Timestamptz>,
+        deleted_at -> Nullable<Timestamptz>,
+        removed_by -> Nullable<Int4>,
+    }
+}
+
+table! {
+    bookmarks (user, article) {
+        user -> Int4,
+        article -> Int4,
+        folder -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    comment_reactions (comment, user, reaction) {
+        comment -> Int4,
+        user -> Int4,
+        reaction -> Text,
+        created_at -> Timestamptz,
     }
 }
 
@@ -21,6 +105,11 @@ table! {
         author -> Int4,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        reaction_counts -> Jsonb,
+        hidden_at -> Nullable<Timestamptz>,
+        deleted_at -> Nullable<Timestamptz>,
+        deleted_by -> Nullable<Int4>,
+        removed_by -> Nullable<Int4>,
     }
 }
 
@@ -28,6 +117,15 @@ table! {
     favorites (user, article) {
         user -> Int4,
         article -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    feed_tokens (user) {
+        user -> Int4,
+        token -> Text,
+        created_at -> Timestamptz,
     }
 }
 
@@ -38,6 +136,104 @@ table! {
     }
 }
 
+table! {
+    related_articles (article, related) {
+        article -> Int4,
+        related -> Int4,
+        score -> Float8,
+    }
+}
+
+table! {
+    related_cache (article) {
+        article -> Int4,
+        computed_at -> Timestamptz,
+    }
+}
+
+table! {
+    reports (id) {
+        id -> Int4,
+        reporter -> Nullable<Int4>,
+        target_type -> Text,
+        target_id -> Int4,
+        reason -> Text,
+        details -> Text,
+        status -> Text,
+        action -> Nullable<Text>,
+        resolved_by -> Nullable<Int4>,
+        resolved_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    series (id) {
+        id -> Int4,
+        slug -> Text,
+        title -> Text,
+        description -> Text,
+        owner -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    series_articles (series, article) {
+        series -> Int4,
+        article -> Int4,
+        position -> Int4,
+    }
+}
+
+table! {
+    sitemap_entries (id) {
+        id -> Int4,
+        kind -> Text,
+        item -> Int4,
+        name -> Text,
+        lastmod -> Timestamptz,
+    }
+}
+
+table! {
+    slug_history (slug) {
+        slug -> Text,
+        article -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    tag_follows (follower, tag) {
+        follower -> Int4,
+        tag -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    tags (id) {
+        id -> Int4,
+        name -> Text,
+        description -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    uploads (id) {
+        id -> Int4,
+        owner -> Int4,
+        hash -> Text,
+        content_type -> Text,
+        size -> Int8,
+        filename -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
 table! {
     users (id) {
         id -> Int4,
@@ -46,13 +242,78 @@ table! {
         bio -> Nullable<Text>,
         image -> Nullable<Text>,
         password -> Text,
+        avatar -> Nullable<Jsonb>,
+        role -> Text,
+        suspended_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    warnings (id) {
+        id -> Int4,
+        user -> Int4,
+        moderator -> Nullable<Int4>,
+        report -> Nullable<Int4>,
+        message -> Text,
+        created_at -> Timestamptz,
     }
 }
 
+joinable!(article_authors -> articles (article));
+joinable!(article_authors -> users (user));
+joinable!(article_cards -> articles (article));
+joinable!(article_reactions -> articles (article));
+joinable!(article_reactions -> users (user));
+joinable!(article_revisions -> articles (article));
+joinable!(article_revisions -> users (author));
+joinable!(article_tags -> articles (article));
+joinable!(article_tags -> tags (tag));
+joinable!(article_views -> articles (article));
 joinable!(articles -> users (author));
+joinable!(bookmarks -> articles (article));
+joinable!(bookmarks -> users (user));
+joinable!(comment_reactions -> comments (comment));
+joinable!(comment_reactions -> users (user));
 joinable!(comments -> articles (article));
 joinable!(comments -> users (author));
 joinable!(favorites -> articles (article));
 joinable!(favorites -> users (user));
+joinable!(feed_tokens -> users (user));
+joinable!(related_cache -> articles (article));
+joinable!(series -> users (owner));
+joinable!(series_articles -> articles (article));
+joinable!(series_articles -> series (series));
+joinable!(slug_history -> articles (article));
+joinable!(tag_follows -> tags (tag));
+joinable!(tag_follows -> users (follower));
+joinable!(uploads -> users (owner));
+joinable!(warnings -> reports (report));
 
-allow_tables_to_appear_in_same_query!(articles, comments, favorites, follows, users,);
+allow_tables_to_appear_in_same_query!(
+    article_authors,
+    article_cards,
+    article_reactions,
+    article_revisions,
+    article_tags,
+    article_views,
+    articles,
+    bookmarks,
+    comment_reactions,
+    comments,
+    favorites,
+    feed_tokens,
+    follows,
+    related_articles,
+    related_cache,
+    reports,
+    series,
+    series_articles,
+    sitemap_entries,
+    slug_history,
+    tag_follows,
+    tags,
+    uploads,
+    users,
+    warnings,
+);
@@ -0,0 +1,160 @@
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Where uploaded files live. Blobs are addressed by the hex SHA-256 of
+/// their content, so storing the same bytes twice is a no-op.
+pub trait BlobStore: Send + Sync {
+    fn put(&self, data: &[u8]) -> io::Result<String>;
+    fn get(&self, key: &str) -> io::Result<Vec<u8>>;
+    fn delete(&self, key: &str) -> io::Result<()>;
+    /// Every key stored or stored again before `before`, for garbage
+    /// collection.
+    fn keys(&self, before: SystemTime) -> io::Result<Vec<String>>;
+}
+
+pub fn key(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+pub fn is_key(key: &str) -> bool {
+    key.len() == 64
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase())
+}
+
+/// Guesses the type of an upload from its first bytes. Only types we are
+/// willing to serve back are recognised.
+pub fn sniff(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else {
+        None
+    }
+}
+
+/// Stores blobs under `root`, fanned out by the first two characters of the key.
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        LocalStore {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        if !is_key(key) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid blob key",
+            ));
+        }
+        Ok(self.root.join(&key[..2]).join(key))
+    }
+}
+
+impl BlobStore for LocalStore {
+    fn put(&self, data: &[u8]) -> io::Result<String> {
+        let key = key(data);
+        let path = self.path(&key)?;
+        if path.exists() {
+            // Mark the file as stored again, so it is not swept before the
+            // upload that now refers to it is recorded.
+            fs::OpenOptions::new()
+                .write(true)
+                .open(&path)?
+                .set_modified(SystemTime::now())?;
+            return Ok(key);
+        }
+        let dir = path.parent().unwrap();
+        fs::create_dir_all(dir)?;
+        // Write to a temporary name first so a crash never leaves a partial
+        // file behind under the real key.
+        let tmp = dir.join(format!(".{}.tmp", key));
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &path)?;
+        Ok(key)
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path(key)?)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn keys(&self, before: SystemTime) -> io::Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let dirs = match fs::read_dir(&self.root) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(keys),
+            dirs => dirs?,
+        };
+        for dir in dirs {
+            let dir = dir?;
+            if !dir.file_type()?.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(dir.path())? {
+                let file = file?;
+                let name = file.file_name().to_string_lossy().into_owned();
+                if is_key(&name) && file.metadata()?.modified()? < before {
+                    keys.push(name);
+                }
+            }
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_supported_types() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("image/png"));
+        assert_eq!(sniff(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some("image/jpeg"));
+        assert_eq!(sniff(b"GIF87a\x01\0"), Some("image/gif"));
+        assert_eq!(sniff(b"GIF89a\x01\0"), Some("image/gif"));
+        assert_eq!(sniff(b"RIFF\x24\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff(b"%PDF-1.7\n"), Some("application/pdf"));
+    }
+
+    #[test]
+    fn rejects_other_content() {
+        assert_eq!(sniff(b""), None);
+        assert_eq!(sniff(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"), None);
+        assert_eq!(sniff(b"<html><script>alert(1)</script>"), None);
+        assert_eq!(sniff(b"RIFF\x24\0\0\0WAVEfmt "), None);
+        assert_eq!(sniff(b"RIFFWEBP"), None);
+        assert_eq!(sniff(b"\x89PNG"), None);
+    }
+
+    #[test]
+    fn keys_are_lowercase_sha256_hex() {
+        let k = key(b"hello");
+        assert!(is_key(&k));
+        assert!(!is_key(&k.to_uppercase()));
+        assert!(!is_key(&k[1..]));
+        assert!(!is_key("../../etc/passwd"));
+    }
+}
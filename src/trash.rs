@@ -0,0 +1,41 @@
+use crate::{
+    db::{article, comment},
+    Pool,
+};
+use actix_web::web;
+use chrono::{Duration, Utc};
+use diesel::{pg::PgConnection, result::Error, Connection};
+
+/// How often the server empties the trash.
+pub const PURGE_INTERVAL_SECS: u64 = 60 * 60;
+
+/// The longest `TRASH_RETENTION_DAYS` can be, well within what dates can
+/// hold.
+pub const MAX_RETENTION_DAYS: i64 = 100 * 365;
+
+/// Deletes for good the articles and comments that have been in the trash
+/// for longer than `retention`. Returns how many of each were purged.
+pub fn purge(conn: &PgConnection, retention: Duration) -> Result<(usize, usize), Error> {
+    let before = Utc::now() - retention;
+    conn.transaction::<_, Error, _>(|| {
+        let comments = comment::purge(conn, before)?;
+        let articles = article::purge(conn, before)?;
+        Ok((articles, comments))
+    })
+}
+
+/// Runs [`purge`] off the async executor, logging the outcome.
+pub async fn purge_in_background(pool: Pool, retention: Duration) {
+    let result = web::block(move || {
+        let conn = pool.get().unwrap();
+        purge(&conn, retention)
+    })
+    .await;
+    match result {
+        Ok((articles, comments)) => debug!(
+            "purged {} articles and {} comments from the trash",
+            articles, comments
+        ),
+        Err(e) => error!("failed to purge the trash: {:?}", e),
+    }
+}
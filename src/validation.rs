@@ -0,0 +1,120 @@
+use crate::{
+    db::{tag, upload},
+    errors::Errors,
+    storage,
+};
+use diesel::{pg::PgConnection, result::Error};
+
+pub const MAX_TITLE_LENGTH: usize = 255;
+pub const MAX_DESCRIPTION_LENGTH: usize = 1000;
+pub const MAX_TAGS: usize = 10;
+pub const MAX_TAG_LENGTH: usize = 32;
+pub const MAX_COVER_IMAGE_LENGTH: usize = 2048;
+
+/// Trims, lowercases and deduplicates tags, dropping empty ones.
+pub fn normalize_tags(list: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(list.len());
+    for tag in list {
+        let tag = tag::normalize(&tag);
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+pub fn validate_tags(errors: &mut Errors, field: &'static str, list: &[String]) {
+    if list.len() > MAX_TAGS {
+        errors.push_error(field, &format!("cannot have more than {} tags", MAX_TAGS));
+    }
+    for tag in list.iter().filter(|t| t.chars().count() > MAX_TAG_LENGTH) {
+        errors.push_error(
+            field,
+            &format!("'{}' is longer than {} characters", tag, MAX_TAG_LENGTH),
+        );
+    }
+}
+
+/// Rejects text longer than `max` characters.
+pub fn validate_length(errors: &mut Errors, field: &'static str, text: &str, max: usize) {
+    if text.chars().count() > max {
+        errors.push_error(field, &format!("cannot be longer than {} characters", max));
+    }
+}
+
+/// Cover images are either uploads or absolute http(s) URLs, so they can be
+/// used as is in social preview metadata.
+pub fn validate_cover_image(errors: &mut Errors, url: &str) {
+    validate_length(errors, "coverImage", url, MAX_COVER_IMAGE_LENGTH);
+    match url.strip_prefix(upload::URL_PREFIX) {
+        Some(hash) if !storage::is_key(hash) => {
+            errors.push_error("coverImage", "is not a valid upload")
+        }
+        Some(_) => {}
+        None if !url.starts_with("https://") && !url.starts_with("http://") => {
+            errors.push_error("coverImage", "must be an http(s) URL or an upload")
+        }
+        None => {}
+    }
+}
+
+/// Rejects a cover image pointing at an upload `owner` did not make, once
+/// [`validate_cover_image`] has accepted it.
+pub fn validate_cover_upload(
+    conn: &PgConnection,
+    errors: &mut Errors,
+    owner: i32,
+    url: &str,
+) -> Result<(), Error> {
+    if let Some(hash) = url.strip_prefix(upload::URL_PREFIX) {
+        if !upload::is_owned(conn, owner, hash)? {
+            errors.push_error("coverImage", "is not one of your uploads");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn normalizes_tags_in_order() {
+        let list = tags(&[" Rust ", "", "web", "RUST", "  ", "Web", "diesel"]);
+        assert_eq!(normalize_tags(list), tags(&["rust", "web", "diesel"]));
+        assert!(normalize_tags(vec![]).is_empty());
+    }
+
+    #[test]
+    fn accepts_tags_within_limits() {
+        let mut errors = Errors::new();
+        let list: Vec<String> = (0..MAX_TAGS).map(|i| "x".repeat(i + 1)).collect();
+        validate_tags(&mut errors, "tagList", &list);
+        validate_tags(&mut errors, "tagList", &["é".repeat(MAX_TAG_LENGTH)]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn reports_each_tag_problem_under_its_field() {
+        let mut errors = Errors::new();
+        let mut list: Vec<String> = (0..MAX_TAGS).map(|i| i.to_string()).collect();
+        list.push("x".repeat(MAX_TAG_LENGTH + 1));
+        validate_tags(&mut errors, "addTags", &list);
+        assert_eq!(
+            serde_json::to_value(&errors).unwrap(),
+            json!({
+                "errors": {
+                    "addTags": [
+                        "cannot have more than 10 tags",
+                        format!("'{}' is longer than 32 characters", "x".repeat(33)),
+                    ]
+                }
+            })
+        );
+    }
+}
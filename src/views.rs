@@ -0,0 +1,89 @@
+use crate::{db::stats, Pool};
+use actix_web::{web, HttpRequest};
+use chrono::{NaiveDate, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{collections::HashSet, mem, net::SocketAddr, sync::Mutex};
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct View {
+    pub article: i32,
+    pub visitor: String,
+    pub viewed_on: NaiveDate,
+}
+
+/// Buffers article views in memory so that reads do not write to the
+/// database. Repeated views by the same visitor on the same day collapse
+/// into one entry, and `flush` writes what was collected in one batch.
+#[derive(Debug)]
+pub struct ViewTracker {
+    pending: Mutex<HashSet<View>>,
+    /// Keys the hash of anonymous visitors, so that it cannot be reversed
+    /// by hashing every address and common user agent.
+    secret: Vec<u8>,
+}
+
+impl ViewTracker {
+    pub fn new(secret: &str) -> Self {
+        ViewTracker {
+            pending: Mutex::default(),
+            secret: secret.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn record(&self, article: i32, visitor: String) {
+        let view = View {
+            article,
+            visitor,
+            viewed_on: Utc::now().naive_utc().date(),
+        };
+        self.pending.lock().unwrap().insert(view);
+    }
+
+    fn take(&self) -> Vec<View> {
+        let mut pending = self.pending.lock().unwrap();
+        mem::take(&mut *pending).into_iter().collect()
+    }
+
+    pub async fn flush(&self, pool: Pool) {
+        let views = self.take();
+        if views.is_empty() {
+            return;
+        }
+        let result = web::block(move || {
+            let conn = pool.get().unwrap();
+            stats::record_views(&conn, &views)
+        })
+        .await;
+        match result {
+            Ok(n) => debug!("flushed {} article views", n),
+            Err(e) => error!("failed to flush article views: {:?}", e),
+        }
+    }
+
+    /// Identifies the visitor behind a request: the user id when logged in,
+    /// otherwise an HMAC of the client address and user agent.
+    pub fn visitor(&self, req: &HttpRequest, user_id: Option<i32>) -> String {
+        if let Some(id) = user_id {
+            return format!("user:{}", id);
+        }
+        let info = req.connection_info();
+        let remote = info.remote().unwrap_or("");
+        let ip = remote
+            .parse::<SocketAddr>()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|_| remote.to_owned());
+        let agent = req
+            .headers()
+            .get("user-agent")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC takes keys of any size");
+        mac.update(ip.as_bytes());
+        mac.update(b"\n");
+        mac.update(agent.as_bytes());
+        format!("anon:{:x}", mac.finalize().into_bytes())
+    }
+}